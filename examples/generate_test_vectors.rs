@@ -0,0 +1,10 @@
+// Prints known-answer vectors for every compiled-in scheme as JSON, e.g.
+// cargo run --example generate_test_vectors --features test-vectors,ed25519-zebra > vectors/hybrid.json
+// Committed vectors must keep replaying: when the format changes, move the old file aside (see
+// vectors/hybrid_v2.json) instead of overwriting it, and only ever add vectors to a file.
+use hybrid_cryptography::test_vectors::{generate_default_test_vectors, test_vectors_to_json};
+
+fn main() {
+    let vectors = generate_default_test_vectors();
+    println!("{}", test_vectors_to_json(&vectors));
+}
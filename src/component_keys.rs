@@ -0,0 +1,336 @@
+use crate::hybrid_signature::{parse_combined_public_key, parse_seed, ComponentEncoding, ParsedCombinedPublicKey};
+use crate::schemes::{key_encapsulation, signature, AlgorithmPurpose, Keypair, SchemeInfo, SizeInfo, SizeKind};
+use crate::secret::SecretBytes;
+#[cfg(feature = "native-key-formats")]
+use crate::self_test::{key_encapsulation_pairwise_consistency_test, signature_pairwise_consistency_test};
+#[cfg(feature = "native-key-formats")]
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+#[cfg(feature = "native-key-formats")]
+use base64::Engine;
+#[cfg(feature = "native-key-formats")]
+use zeroize::Zeroizing;
+
+// Everything the export functions need to know about a registry entry
+struct ComponentScheme {
+    scheme_id: (u16, u16),
+    scheme_info: SchemeInfo,
+    #[allow(dead_code)]
+    openssh_key_type: Option<&'static str>,
+    #[allow(dead_code)]
+    jwk_curve: Option<&'static str>,
+}
+
+fn lookup_component_scheme(purpose: AlgorithmPurpose, name: &str) -> Result<ComponentScheme, String> {
+    let not_found = || format!("Algorithm {} not found", name);
+    return match purpose {
+        AlgorithmPurpose::Signature => {
+            let mapping = signature::get_name_to_ref_mapping();
+            let scheme_ref = mapping.get(name).ok_or_else(not_found)?;
+            Ok(ComponentScheme {
+                scheme_id: (scheme_ref.scheme_id, scheme_ref.scheme_config_id),
+                scheme_info: scheme_ref.scheme_impl.get_scheme_info(),
+                openssh_key_type: scheme_ref.scheme_impl.openssh_key_type(),
+                jwk_curve: scheme_ref.scheme_impl.jwk_curve(),
+            })
+        }
+        AlgorithmPurpose::KeyEncapsulation => {
+            let mapping = key_encapsulation::get_name_to_ref_mapping();
+            let scheme_ref = mapping.get(name).ok_or_else(not_found)?;
+            Ok(ComponentScheme {
+                scheme_id: (scheme_ref.scheme_id, scheme_ref.scheme_config_id),
+                scheme_info: scheme_ref.scheme_impl.get_scheme_info(),
+                openssh_key_type: None,
+                jwk_curve: scheme_ref.scheme_impl.jwk_curve(),
+            })
+        }
+    };
+}
+
+fn fits_size(size_info: &SizeInfo, key: &[u8]) -> bool {
+    return match size_info.kind {
+        SizeKind::FixedSized => Some(key.len() as u32) == size_info.fixed_size,
+        SizeKind::VariableSized => key.len() as u32 <= size_info.max_size.unwrap_or(0),
+    };
+}
+
+fn check_public_key_size(scheme: &ComponentScheme, name: &str, public_key: &[u8]) -> Result<(), String> {
+    if !fits_size(&scheme.scheme_info.pk_size_info, public_key) {
+        return Err(format!("Public key of {} has the wrong size", name));
+    }
+    return Ok(());
+}
+
+#[cfg(feature = "native-key-formats")]
+fn check_secret_key_size(scheme: &ComponentScheme, name: &str, secret_key: &[u8]) -> Result<(), String> {
+    if !fits_size(&scheme.scheme_info.sk_size_info, secret_key) {
+        return Err(format!("Secret key of {} has the wrong size", name));
+    }
+    return Ok(());
+}
+
+// Checks that a secret key belongs to a public key with the same tests imported keypairs go through
+#[cfg(feature = "native-key-formats")]
+fn check_component_keypair(purpose: AlgorithmPurpose, name: &str, keypair: &Keypair) -> Result<(), String> {
+    let result = match purpose {
+        AlgorithmPurpose::Signature => signature_pairwise_consistency_test(signature::get_name_to_ref_mapping()[name].scheme_impl.as_ref(), keypair),
+        AlgorithmPurpose::KeyEncapsulation => key_encapsulation_pairwise_consistency_test(key_encapsulation::get_name_to_ref_mapping()[name].scheme_impl.as_ref(), keypair),
+    };
+    return result.map_err(|_| format!("Secret key of {} does not belong to its public key", name));
+}
+
+// Raw public key of one component, in the scheme's own encoding
+pub fn component_public_key(combined_public_key: &ParsedCombinedPublicKey, name: &str) -> Result<Vec<u8>, String> {
+    let scheme = lookup_component_scheme(combined_public_key.purpose, name)?;
+    return match combined_public_key.get(&scheme.scheme_id) {
+        Some(public_key) => Ok(public_key.to_vec()),
+        None => Err(format!("Combined public key has no {} component", name)),
+    };
+}
+
+// Raw secret key of one component, derived from the seed like the hybrid operations do
+pub fn component_secret_key(seed: &[u8], purpose: AlgorithmPurpose, name: &str) -> Result<SecretBytes, String> {
+    let scheme = lookup_component_scheme(purpose, name)?;
+    let parsed_seed = parse_seed(seed)?;
+    let scheme_ids = match purpose {
+        AlgorithmPurpose::Signature => &parsed_seed.signature_scheme_ids,
+        AlgorithmPurpose::KeyEncapsulation => &parsed_seed.key_encapsulation_scheme_ids,
+    };
+    if !scheme_ids.contains(&scheme.scheme_id) {
+        return Err(format!("Seed has no {} component", name));
+    }
+    let seed_branch = parsed_seed.scheme_seed_branch(purpose, scheme.scheme_id)?;
+    let keypair: Keypair = match purpose {
+        AlgorithmPurpose::Signature => signature::get_name_to_ref_mapping()[name].scheme_impl.generate_keypair(&seed_branch),
+        AlgorithmPurpose::KeyEncapsulation => key_encapsulation::get_name_to_ref_mapping()[name].scheme_impl.generate_keypair(&seed_branch),
+    };
+    return Ok(keypair.sk.clone().into());
+}
+
+// Assembles a combined public key from raw component keys, in canonical order
+pub fn combined_public_key_from_components(purpose: AlgorithmPurpose, components: &[(&str, &[u8])]) -> Result<ParsedCombinedPublicKey, String> {
+    let mut parsed_components = Vec::new();
+    for (name, public_key) in components.iter() {
+        let scheme = lookup_component_scheme(purpose, name)?;
+        check_public_key_size(&scheme, name, public_key)?;
+        parsed_components.push((scheme.scheme_id, public_key.to_vec()));
+    }
+    parsed_components.sort_by_key(|(scheme_id, _)| *scheme_id);
+    let parsed = ParsedCombinedPublicKey::new(purpose, ComponentEncoding::Varint, parsed_components);
+    // Rejects duplicates the same way parsing an encoded key does
+    parse_combined_public_key(purpose, &parsed.encode()?)?;
+    return Ok(parsed);
+}
+
+#[cfg(feature = "native-key-formats")]
+fn push_ssh_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend((bytes.len() as u32).to_be_bytes());
+    out.extend(bytes);
+}
+
+#[cfg(feature = "native-key-formats")]
+fn read_ssh_string<'a>(bytes: &'a [u8], idx: &mut usize) -> Result<&'a [u8], String> {
+    let len = match bytes.get(*idx..*idx + 4) {
+        Some(len) => u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize,
+        None => return Err("OpenSSH key blob is truncated".to_string()),
+    };
+    *idx += 4;
+    let value = match bytes.get(*idx..*idx + len) {
+        Some(value) => value,
+        None => return Err("OpenSSH key blob is truncated".to_string()),
+    };
+    *idx += len;
+    return Ok(value);
+}
+
+#[cfg(feature = "native-key-formats")]
+fn openssh_key_type(scheme: &ComponentScheme, name: &str) -> Result<&'static str, String> {
+    return scheme.openssh_key_type.ok_or_else(|| format!("{} has no OpenSSH key format", name));
+}
+
+#[cfg(feature = "native-key-formats")]
+fn jwk_curve(scheme: &ComponentScheme, name: &str) -> Result<&'static str, String> {
+    return scheme.jwk_curve.ok_or_else(|| format!("{} has no JSON Web Key format", name));
+}
+
+// OpenSSH public key line, e.g. "ssh-ed25519 AAAA... comment"
+#[cfg(feature = "native-key-formats")]
+pub fn component_public_key_to_openssh(name: &str, public_key: &[u8], comment: &str) -> Result<String, String> {
+    let scheme = lookup_component_scheme(AlgorithmPurpose::Signature, name)?;
+    let key_type = openssh_key_type(&scheme, name)?;
+    check_public_key_size(&scheme, name, public_key)?;
+    let mut blob = Vec::new();
+    push_ssh_string(&mut blob, key_type.as_bytes());
+    push_ssh_string(&mut blob, public_key);
+    let line = format!("{} {}", key_type, STANDARD.encode(blob));
+    if comment.is_empty() {
+        return Ok(line);
+    }
+    return Ok(format!("{} {}", line, comment));
+}
+
+#[cfg(feature = "native-key-formats")]
+pub fn component_public_key_from_openssh(name: &str, line: &str) -> Result<Vec<u8>, String> {
+    let scheme = lookup_component_scheme(AlgorithmPurpose::Signature, name)?;
+    let key_type = openssh_key_type(&scheme, name)?;
+    let mut fields = line.split_whitespace();
+    if fields.next() != Some(key_type) {
+        return Err(format!("Expected an OpenSSH {} key", key_type));
+    }
+    let blob = match fields.next().map(|field| STANDARD.decode(field)) {
+        Some(Ok(blob)) => blob,
+        _ => return Err("OpenSSH key blob is not valid base64".to_string()),
+    };
+    let mut idx = 0;
+    if read_ssh_string(&blob, &mut idx)? != key_type.as_bytes() {
+        return Err("OpenSSH key blob does not match its key type".to_string());
+    }
+    let public_key = read_ssh_string(&blob, &mut idx)?.to_vec();
+    if idx != blob.len() {
+        return Err("OpenSSH key blob has trailing bytes".to_string());
+    }
+    check_public_key_size(&scheme, name, &public_key)?;
+    return Ok(public_key);
+}
+
+// OKP JSON Web Keys as in RFC 8037
+#[cfg(feature = "native-key-formats")]
+pub fn component_public_key_to_jwk(purpose: AlgorithmPurpose, name: &str, public_key: &[u8]) -> Result<String, String> {
+    let scheme = lookup_component_scheme(purpose, name)?;
+    let curve = jwk_curve(&scheme, name)?;
+    check_public_key_size(&scheme, name, public_key)?;
+    let jwk = serde_json::json!({
+        "kty": "OKP",
+        "crv": curve,
+        "x": URL_SAFE_NO_PAD.encode(public_key),
+    });
+    return Ok(jwk.to_string());
+}
+
+#[cfg(feature = "native-key-formats")]
+pub fn component_secret_key_to_jwk(purpose: AlgorithmPurpose, name: &str, public_key: &[u8], secret_key: &SecretBytes) -> Result<Zeroizing<String>, String> {
+    let scheme = lookup_component_scheme(purpose, name)?;
+    let curve = jwk_curve(&scheme, name)?;
+    check_public_key_size(&scheme, name, public_key)?;
+    let d = Zeroizing::new(URL_SAFE_NO_PAD.encode(secret_key.as_bytes()));
+    let jwk = format!(
+        "{{\"kty\":\"OKP\",\"crv\":\"{}\",\"x\":\"{}\",\"d\":\"{}\"}}",
+        curve,
+        URL_SAFE_NO_PAD.encode(public_key),
+        d.as_str()
+    );
+    return Ok(Zeroizing::new(jwk));
+}
+
+#[cfg(feature = "native-key-formats")]
+fn read_jwk_member(jwk: &serde_json::Value, member: &str) -> Result<Vec<u8>, String> {
+    let value = match jwk.get(member).and_then(|value| value.as_str()) {
+        Some(value) => value,
+        None => return Err(format!("JSON Web Key has no {} member", member)),
+    };
+    return URL_SAFE_NO_PAD.decode(value).map_err(|_| format!("JSON Web Key member {} is not valid base64url", member));
+}
+
+#[cfg(feature = "native-key-formats")]
+fn parse_jwk(scheme: &ComponentScheme, name: &str, jwk: &str) -> Result<serde_json::Value, String> {
+    let curve = jwk_curve(scheme, name)?;
+    let jwk: serde_json::Value = serde_json::from_str(jwk).map_err(|e| format!("Invalid JSON Web Key: {}", e))?;
+    if jwk.get("kty").and_then(|kty| kty.as_str()) != Some("OKP") || jwk.get("crv").and_then(|crv| crv.as_str()) != Some(curve) {
+        return Err(format!("Expected an OKP JSON Web Key for {}", curve));
+    }
+    return Ok(jwk);
+}
+
+#[cfg(feature = "native-key-formats")]
+pub fn component_public_key_from_jwk(purpose: AlgorithmPurpose, name: &str, jwk: &str) -> Result<Vec<u8>, String> {
+    let scheme = lookup_component_scheme(purpose, name)?;
+    let jwk = parse_jwk(&scheme, name, jwk)?;
+    let public_key = read_jwk_member(&jwk, "x")?;
+    check_public_key_size(&scheme, name, &public_key)?;
+    return Ok(public_key);
+}
+
+#[cfg(feature = "native-key-formats")]
+pub fn component_secret_key_from_jwk(purpose: AlgorithmPurpose, name: &str, jwk: &str) -> Result<SecretBytes, String> {
+    let scheme = lookup_component_scheme(purpose, name)?;
+    let jwk = parse_jwk(&scheme, name, jwk)?;
+    let keypair = Keypair {
+        pk: read_jwk_member(&jwk, "x")?,
+        sk: read_jwk_member(&jwk, "d")?,
+    };
+    check_public_key_size(&scheme, name, &keypair.pk)?;
+    check_secret_key_size(&scheme, name, &keypair.sk)?;
+    check_component_keypair(purpose, name, &keypair)?;
+    return Ok(keypair.sk.clone().into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_signature::HybridSeed;
+    use crate::test_utils::{test_seed, test_seed_with_all_schemes};
+
+    #[test]
+    fn test_raw_component_keys_round_trip() {
+        let seed = test_seed_with_all_schemes();
+        for (purpose, names) in [
+            (AlgorithmPurpose::Signature, signature::get_name_to_ref_mapping().into_keys().collect::<Vec<String>>()),
+            (AlgorithmPurpose::KeyEncapsulation, key_encapsulation::get_name_to_ref_mapping().into_keys().collect()),
+        ] {
+            let parsed = match purpose {
+                AlgorithmPurpose::Signature => seed.signature_public_key().unwrap().parse(),
+                AlgorithmPurpose::KeyEncapsulation => seed.key_encapsulation_public_key().unwrap().parse(),
+            };
+            let mut public_keys = Vec::new();
+            for name in names.iter() {
+                public_keys.push(component_public_key(&parsed, name).unwrap());
+                assert!(component_secret_key(seed.as_bytes(), purpose, name).is_ok());
+            }
+            let components: Vec<(&str, &[u8])> = names.iter().map(|name| name.as_str()).zip(public_keys.iter().map(|pk| pk.as_slice())).collect();
+            let rebuilt = combined_public_key_from_components(purpose, &components).unwrap();
+            assert_eq!(rebuilt.encode(), parsed.encode());
+            if let Some(component) = components.first() {
+                assert!(combined_public_key_from_components(purpose, &[*component, *component]).is_err());
+                assert!(combined_public_key_from_components(purpose, &[(component.0, &component.1[1..])]).is_err());
+            }
+        }
+        assert!(component_public_key(&seed.signature_public_key().unwrap().parse(), "unknown").is_err());
+        let empty_seed = HybridSeed::wrap(&[], &[], &test_seed).unwrap();
+        for name in signature::get_name_to_ref_mapping().keys() {
+            assert!(component_secret_key(empty_seed.as_bytes(), AlgorithmPurpose::Signature, name).is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "ed25519-zebra", feature = "native-key-formats"))]
+    fn test_ed25519_native_formats() {
+        use hex_literal::hex;
+        // RFC 8032 test 1
+        let public_key = hex!("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+        let secret_key: SecretBytes = hex!("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").to_vec().into();
+
+        let line = component_public_key_to_openssh("ed25519-zebra", &public_key, "user@host").unwrap();
+        assert!(line.starts_with("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAI"));
+        assert!(line.ends_with(" user@host"));
+        assert_eq!(component_public_key_from_openssh("ed25519-zebra", &line).unwrap(), public_key);
+        assert!(component_public_key_from_openssh("ed25519-zebra", &line.replace("ssh-ed25519", "ssh-rsa")).is_err());
+
+        let jwk = component_public_key_to_jwk(AlgorithmPurpose::Signature, "ed25519-zebra", &public_key).unwrap();
+        assert_eq!(jwk, r#"{"crv":"Ed25519","kty":"OKP","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#);
+        assert_eq!(component_public_key_from_jwk(AlgorithmPurpose::Signature, "ed25519-zebra", &jwk).unwrap(), public_key);
+        let secret_jwk = component_secret_key_to_jwk(AlgorithmPurpose::Signature, "ed25519-zebra", &public_key, &secret_key).unwrap();
+        assert!(secret_jwk.contains(r#""d":"nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A""#));
+        assert_eq!(component_secret_key_from_jwk(AlgorithmPurpose::Signature, "ed25519-zebra", &secret_jwk).unwrap(), secret_key);
+        assert!(component_secret_key_from_jwk(AlgorithmPurpose::Signature, "ed25519-zebra", &jwk).is_err());
+        let short_d = secret_jwk.replace("nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A", "nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyufw");
+        assert!(component_secret_key_from_jwk(AlgorithmPurpose::Signature, "ed25519-zebra", &short_d).is_err());
+        let other_d = secret_jwk.replace("nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A", "nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2E");
+        assert!(component_secret_key_from_jwk(AlgorithmPurpose::Signature, "ed25519-zebra", &other_d).is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "saber", feature = "native-key-formats"))]
+    fn test_formats_without_native_encoding() {
+        assert!(component_public_key_to_jwk(AlgorithmPurpose::KeyEncapsulation, "firesaber", &[0u8; 1312]).is_err());
+        assert!(component_public_key_to_openssh("firesaber", &[0u8; 1312], "").is_err());
+    }
+}
@@ -0,0 +1,355 @@
+use crate::hybrid_signature::{CombinedPublicKey, HybridCiphertext, HybridDecapsulator, HybridSignature, HybridSigner, KeyEncapsulationPublicKey, KeyPurpose, SharedSecret, SignaturePublicKey};
+use crate::schemes::AlgorithmPurpose;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ciborium::value::Value;
+use rand_core::{CryptoRngCore, OsRng};
+
+// Hybrid keys and algorithms have no IANA registered COSE values, so they use the private-use
+// range below -65536. The content encryption algorithm is the registered ChaCha20/Poly1305.
+pub const COSE_KEY_TYPE_HYBRID: i64 = -65537;
+pub const COSE_ALGORITHM_HYBRID_SIGNATURE: i64 = -65537;
+pub const COSE_ALGORITHM_HYBRID_KEY_ENCAPSULATION: i64 = -65538;
+pub const COSE_ALGORITHM_CHACHA20_POLY1305: i64 = 24;
+// Unprotected header parameter carrying the hybrid ciphertext of an Encrypt0 message
+pub const COSE_HEADER_HYBRID_CIPHERTEXT: i64 = -65537;
+
+const COSE_SIGN1_TAG: u64 = 18;
+const COSE_ENCRYPT0_TAG: u64 = 16;
+
+const KEY_TYPE_LABEL: i64 = 1;
+const KEY_ID_LABEL: i64 = 2;
+const KEY_ALGORITHM_LABEL: i64 = 3;
+const KEY_PUBLIC_KEY_LABEL: i64 = -1;
+const HEADER_ALGORITHM_LABEL: i64 = 1;
+const HEADER_KEY_ID_LABEL: i64 = 4;
+const HEADER_IV_LABEL: i64 = 5;
+
+const CHACHA20_POLY1305_NONCE_LEN: usize = 12;
+
+fn to_cbor(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(value, &mut bytes).expect("Writing CBOR to a vector does not fail");
+    return bytes;
+}
+
+fn from_cbor(bytes: &[u8], what: &str) -> Result<Value, String> {
+    return ciborium::de::from_reader(bytes).map_err(|e| format!("Invalid {}: {}", what, e));
+}
+
+fn label(label: i64) -> Value {
+    return Value::Integer(label.into());
+}
+
+fn map_get(map: &[(Value, Value)], wanted: i64) -> Option<&Value> {
+    return map
+        .iter()
+        .find(|(key, _)| matches!(key, Value::Integer(key) if i128::from(*key) == wanted as i128))
+        .map(|(_, value)| value);
+}
+
+fn map_get_bytes<'a>(map: &'a [(Value, Value)], wanted: i64, what: &str) -> Result<&'a [u8], String> {
+    return match map_get(map, wanted) {
+        Some(Value::Bytes(bytes)) => Ok(bytes),
+        Some(_) => Err(format!("{} must be a byte string", what)),
+        None => Err(format!("{} is missing", what)),
+    };
+}
+
+fn map_get_integer(map: &[(Value, Value)], wanted: i64) -> Option<i128> {
+    return match map_get(map, wanted) {
+        Some(Value::Integer(value)) => Some(i128::from(*value)),
+        _ => None,
+    };
+}
+
+fn as_map<'a>(value: &'a Value, what: &str) -> Result<&'a [(Value, Value)], String> {
+    return match value {
+        Value::Map(map) => Ok(map),
+        _ => Err(format!("{} must be a map", what)),
+    };
+}
+
+fn cose_algorithm(purpose: AlgorithmPurpose) -> i64 {
+    return match purpose {
+        AlgorithmPurpose::Signature => COSE_ALGORITHM_HYBRID_SIGNATURE,
+        AlgorithmPurpose::KeyEncapsulation => COSE_ALGORITHM_HYBRID_KEY_ENCAPSULATION,
+    };
+}
+
+impl<P: KeyPurpose> CombinedPublicKey<P> {
+    // COSE_Key with the address as key id and the purpose in the algorithm
+    pub fn to_cose_key(&self) -> Vec<u8> {
+        let key = Value::Map(vec![
+            (label(KEY_TYPE_LABEL), label(COSE_KEY_TYPE_HYBRID)),
+            (label(KEY_ID_LABEL), Value::Bytes(self.address().as_bytes().to_vec())),
+            (label(KEY_ALGORITHM_LABEL), label(cose_algorithm(P::PURPOSE))),
+            (label(KEY_PUBLIC_KEY_LABEL), Value::Bytes(self.as_bytes().to_vec())),
+        ]);
+        return to_cbor(&key);
+    }
+
+    pub fn from_cose_key(cose_key: &[u8]) -> Result<Self, String> {
+        let key = from_cbor(cose_key, "COSE_Key")?;
+        let key = as_map(&key, "COSE_Key")?;
+        if map_get_integer(key, KEY_TYPE_LABEL) != Some(COSE_KEY_TYPE_HYBRID as i128) {
+            return Err("COSE_Key is not a hybrid key".to_string());
+        }
+        if let Some(algorithm) = map_get(key, KEY_ALGORITHM_LABEL) {
+            if algorithm != &label(cose_algorithm(P::PURPOSE)) {
+                return Err(format!("COSE_Key is not a {:?} key", P::PURPOSE));
+            }
+        }
+        let public_key = CombinedPublicKey::<P>::from_bytes(map_get_bytes(key, KEY_PUBLIC_KEY_LABEL, "COSE_Key public key")?)?;
+        if map_get(key, KEY_ID_LABEL).is_some() && map_get_bytes(key, KEY_ID_LABEL, "COSE_Key key id")? != public_key.address().as_bytes() {
+            return Err("COSE_Key key id does not match its public key".to_string());
+        }
+        return Ok(public_key);
+    }
+}
+
+// A tagged or untagged COSE message split into its headers and the remaining fields
+struct CoseMessage {
+    protected: Vec<u8>,
+    protected_header: Vec<(Value, Value)>,
+    unprotected_header: Vec<(Value, Value)>,
+    fields: Vec<Value>,
+}
+
+fn split_cose_message(message: &[u8], tag: u64, what: &str) -> Result<CoseMessage, String> {
+    let value = from_cbor(message, what)?;
+    let value = match value {
+        Value::Tag(found_tag, value) if found_tag == tag => *value,
+        Value::Tag(found_tag, _) => return Err(format!("{} has tag {}, expected {}", what, found_tag, tag)),
+        value => value,
+    };
+    let mut fields = match value {
+        Value::Array(fields) => fields.into_iter(),
+        _ => return Err(format!("{} must be an array", what)),
+    };
+    let protected = match fields.next() {
+        Some(Value::Bytes(protected)) => protected,
+        _ => return Err(format!("{} protected header must be a byte string", what)),
+    };
+    let protected_header = match protected.is_empty() {
+        true => Vec::new(),
+        false => as_map(&from_cbor(&protected, "protected header")?, "Protected header")?.to_vec(),
+    };
+    let unprotected_header = match fields.next() {
+        Some(Value::Map(unprotected_header)) => unprotected_header,
+        _ => return Err(format!("{} unprotected header must be a map", what)),
+    };
+    return Ok(CoseMessage {
+        protected,
+        protected_header,
+        unprotected_header,
+        fields: fields.collect(),
+    });
+}
+
+fn protected_header(algorithm: i64) -> Vec<u8> {
+    return to_cbor(&Value::Map(vec![(label(HEADER_ALGORITHM_LABEL), label(algorithm))]));
+}
+
+fn sig_structure(protected: &[u8], external_aad: &[u8], payload: &[u8]) -> Vec<u8> {
+    return to_cbor(&Value::Array(vec![
+        Value::Text("Signature1".to_string()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(external_aad.to_vec()),
+        Value::Bytes(payload.to_vec()),
+    ]));
+}
+
+fn enc_structure(protected: &[u8], external_aad: &[u8]) -> Vec<u8> {
+    return to_cbor(&Value::Array(vec![
+        Value::Text("Encrypt0".to_string()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(external_aad.to_vec()),
+    ]));
+}
+
+// Tagged COSE_Sign1 with an attached payload; the key id is the signer's address
+pub fn cose_sign1<S: HybridSigner>(signer: &S, payload: &[u8], external_aad: &[u8]) -> Result<Vec<u8>, String> {
+    let protected = protected_header(COSE_ALGORITHM_HYBRID_SIGNATURE);
+    let signature = signer.sign(&sig_structure(&protected, external_aad, payload))?;
+    let key_id = signer.signature_public_key()?.address().as_bytes().to_vec();
+    let message = Value::Array(vec![
+        Value::Bytes(protected),
+        Value::Map(vec![(label(HEADER_KEY_ID_LABEL), Value::Bytes(key_id))]),
+        Value::Bytes(payload.to_vec()),
+        Value::Bytes(signature.as_bytes().to_vec()),
+    ]);
+    return Ok(to_cbor(&Value::Tag(COSE_SIGN1_TAG, Box::new(message))));
+}
+
+// Returns the payload if every component of public_key signed it
+pub fn cose_verify_sign1(public_key: &SignaturePublicKey, message: &[u8], external_aad: &[u8]) -> Result<Vec<u8>, String> {
+    let message = split_cose_message(message, COSE_SIGN1_TAG, "COSE_Sign1")?;
+    if map_get_integer(&message.protected_header, HEADER_ALGORITHM_LABEL) != Some(COSE_ALGORITHM_HYBRID_SIGNATURE as i128) {
+        return Err("COSE_Sign1 is not signed with a hybrid signature".to_string());
+    }
+    let (payload, signature) = match message.fields.as_slice() {
+        [Value::Bytes(payload), Value::Bytes(signature)] => (payload, signature),
+        [Value::Null, _] => return Err("Detached payloads are not supported".to_string()),
+        _ => return Err("COSE_Sign1 must hold a payload and a signature".to_string()),
+    };
+    let signature = HybridSignature::from_bytes(signature)?;
+    if !public_key.verify(&sig_structure(&message.protected, external_aad, payload), &signature) {
+        return Err("Signature verification failed".to_string());
+    }
+    return Ok(payload.clone());
+}
+
+// COSE_Encrypt0 whose content key is the shared secret of a fresh hybrid encapsulation to the
+// recipient. The hybrid ciphertext travels in the unprotected header, next to the recipient's
+// address as key id.
+pub fn cose_encrypt0(recipient: &KeyEncapsulationPublicKey, plaintext: &[u8], external_aad: &[u8]) -> Result<Vec<u8>, String> {
+    check_recipient(recipient)?;
+    let (shared_secret, hybrid_ciphertext) = recipient.encapsulate()?;
+    return Ok(encrypt0(recipient, &shared_secret, &hybrid_ciphertext, plaintext, external_aad, &mut OsRng));
+}
+
+// Like cose_encrypt0, but the encapsulation and the nonce both draw from rng
+pub fn cose_encrypt0_with_rng(recipient: &KeyEncapsulationPublicKey, plaintext: &[u8], external_aad: &[u8], rng: &mut dyn CryptoRngCore) -> Result<Vec<u8>, String> {
+    check_recipient(recipient)?;
+    let (shared_secret, hybrid_ciphertext) = recipient.encapsulate_with_rng(rng)?;
+    return Ok(encrypt0(recipient, &shared_secret, &hybrid_ciphertext, plaintext, external_aad, rng));
+}
+
+// Without components the shared secret is a public constant, so the content would be readable by anyone
+fn check_recipient(recipient: &KeyEncapsulationPublicKey) -> Result<(), String> {
+    if recipient.parse().components.is_empty() {
+        return Err("COSE_Encrypt0 recipient key has no components".to_string());
+    }
+    return Ok(());
+}
+
+fn encrypt0(recipient: &KeyEncapsulationPublicKey, shared_secret: &SharedSecret, hybrid_ciphertext: &HybridCiphertext, plaintext: &[u8], external_aad: &[u8], rng: &mut dyn CryptoRngCore) -> Vec<u8> {
+    let mut nonce = [0u8; CHACHA20_POLY1305_NONCE_LEN];
+    rng.fill_bytes(&mut nonce);
+    let protected = protected_header(COSE_ALGORITHM_CHACHA20_POLY1305);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(shared_secret.as_bytes()));
+    let aad = enc_structure(&protected, external_aad);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: &aad })
+        .expect("ChaCha20Poly1305 encryption does not fail");
+    let unprotected = vec![
+        (label(HEADER_KEY_ID_LABEL), Value::Bytes(recipient.address().as_bytes().to_vec())),
+        (label(HEADER_IV_LABEL), Value::Bytes(nonce.to_vec())),
+        (label(COSE_HEADER_HYBRID_CIPHERTEXT), Value::Bytes(hybrid_ciphertext.as_bytes().to_vec())),
+    ];
+    let message = Value::Array(vec![Value::Bytes(protected), Value::Map(unprotected), Value::Bytes(ciphertext)]);
+    return to_cbor(&Value::Tag(COSE_ENCRYPT0_TAG, Box::new(message)));
+}
+
+pub fn cose_decrypt0<D: HybridDecapsulator>(recipient: &D, message: &[u8], external_aad: &[u8]) -> Result<Vec<u8>, String> {
+    let message = split_cose_message(message, COSE_ENCRYPT0_TAG, "COSE_Encrypt0")?;
+    if map_get_integer(&message.protected_header, HEADER_ALGORITHM_LABEL) != Some(COSE_ALGORITHM_CHACHA20_POLY1305 as i128) {
+        return Err("COSE_Encrypt0 is not encrypted with ChaCha20/Poly1305".to_string());
+    }
+    let ciphertext = match message.fields.as_slice() {
+        [Value::Bytes(ciphertext)] => ciphertext,
+        _ => return Err("COSE_Encrypt0 must hold a ciphertext".to_string()),
+    };
+    let nonce = map_get_bytes(&message.unprotected_header, HEADER_IV_LABEL, "COSE_Encrypt0 IV")?;
+    if nonce.len() != CHACHA20_POLY1305_NONCE_LEN {
+        return Err(format!("COSE_Encrypt0 IV must be {} bytes", CHACHA20_POLY1305_NONCE_LEN));
+    }
+    let hybrid_ciphertext = HybridCiphertext::from_bytes(map_get_bytes(&message.unprotected_header, COSE_HEADER_HYBRID_CIPHERTEXT, "COSE_Encrypt0 hybrid ciphertext")?)?;
+    if hybrid_ciphertext.as_bytes().is_empty() {
+        return Err("COSE_Encrypt0 hybrid ciphertext is empty".to_string());
+    }
+    // Only decapsulate ciphertexts made for exactly the recipient's schemes
+    let mut scheme_ids = recipient.key_encapsulation_public_key()?.parse().scheme_ids();
+    let mut encapsulated_scheme_ids = hybrid_ciphertext.scheme_ids();
+    scheme_ids.sort();
+    encapsulated_scheme_ids.sort();
+    if scheme_ids != encapsulated_scheme_ids {
+        return Err("Hybrid ciphertext does not match the recipient's key".to_string());
+    }
+    let shared_secret = recipient.decapsulate(&hybrid_ciphertext)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(shared_secret.as_bytes()));
+    let aad = enc_structure(&message.protected, external_aad);
+    return cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+        .map_err(|_| "Decryption failed".to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_signature::{HybridKeyBundle, HybridSeed};
+    use crate::schemes::key_encapsulation;
+    use crate::test_utils::{increment_bytes, test_seed, test_seed_with_all_schemes};
+
+    #[test]
+    fn test_cose_key_round_trip() {
+        let seed = test_seed_with_all_schemes();
+        let signature_public_key = seed.signature_public_key().unwrap();
+        let cose_key = signature_public_key.to_cose_key();
+        assert_eq!(SignaturePublicKey::from_cose_key(&cose_key), Ok(signature_public_key));
+        assert!(KeyEncapsulationPublicKey::from_cose_key(&cose_key).is_err());
+        let key_encapsulation_public_key = seed.key_encapsulation_public_key().unwrap();
+        let cose_key = key_encapsulation_public_key.to_cose_key();
+        assert_eq!(KeyEncapsulationPublicKey::from_cose_key(&cose_key), Ok(key_encapsulation_public_key));
+        assert!(SignaturePublicKey::from_cose_key(&[0xa0]).is_err());
+    }
+
+    #[test]
+    fn test_cose_sign1_round_trip() {
+        let seed = test_seed_with_all_schemes();
+        let public_key = seed.signature_public_key().unwrap();
+        let message = cose_sign1(&seed, b"sensor reading", b"gateway-7").unwrap();
+        // Tag 18 followed by an array of four
+        assert_eq!(&message[..2], &[0xd2, 0x84]);
+        assert_eq!(cose_verify_sign1(&public_key, &message, b"gateway-7"), Ok(b"sensor reading".to_vec()));
+        assert!(cose_verify_sign1(&public_key, &message, b"gateway-8").is_err());
+        let bundle = HybridKeyBundle::from_seed(&seed).unwrap();
+        assert!(cose_verify_sign1(&public_key, &cose_sign1(&bundle, b"sensor reading", b"").unwrap(), b"").is_ok());
+
+        // Swapping the payload breaks the signature
+        let value: Value = ciborium::de::from_reader(&message[..]).unwrap();
+        if let Value::Tag(_, fields) = value {
+            if let Value::Array(mut fields) = *fields {
+                fields[2] = Value::Bytes(b"forged reading".to_vec());
+                let forged = to_cbor(&Value::Tag(COSE_SIGN1_TAG, Box::new(Value::Array(fields))));
+                assert_eq!(cose_verify_sign1(&public_key, &forged, b"gateway-7").is_err(), public_key.parse().component_count() > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cose_encrypt0_round_trip() {
+        let key_encapsulation_names: Vec<String> = key_encapsulation::get_name_to_ref_mapping().keys().cloned().collect();
+        if key_encapsulation_names.is_empty() {
+            return;
+        }
+        let seed = test_seed_with_all_schemes();
+        let public_key = seed.key_encapsulation_public_key().unwrap();
+        let message = cose_encrypt0(&public_key, b"firmware chunk", b"aad").unwrap();
+        assert_eq!(message[0], 0xd0);
+        assert_eq!(cose_decrypt0(&seed, &message, b"aad"), Ok(b"firmware chunk".to_vec()));
+        assert_eq!(cose_decrypt0(&HybridKeyBundle::from_seed(&seed).unwrap(), &message, b"aad"), Ok(b"firmware chunk".to_vec()));
+        assert!(cose_decrypt0(&seed, &message, b"other aad").is_err());
+        assert!(cose_decrypt0(&seed, &message[..message.len() - 1], b"aad").is_err());
+
+        let mut other_seed = test_seed.to_vec();
+        increment_bytes(&mut other_seed, 1);
+        let other_recipient = HybridSeed::wrap(&[], &key_encapsulation_names, &other_seed).unwrap();
+        assert!(cose_decrypt0(&other_recipient, &message, b"aad").is_err());
+        let no_schemes = HybridSeed::wrap(&[], &[], &other_seed).unwrap();
+        assert!(cose_decrypt0(&no_schemes, &message, b"aad").is_err());
+    }
+
+    #[test]
+    fn test_cose_encrypt0_rejects_empty_keys() {
+        let no_schemes = HybridSeed::wrap(&[], &[], &test_seed).unwrap();
+        let public_key = no_schemes.key_encapsulation_public_key().unwrap();
+        assert!(cose_encrypt0(&public_key, b"secret firmware", b"").is_err());
+        assert!(cose_encrypt0_with_rng(&public_key, b"secret firmware", b"", &mut OsRng).is_err());
+        // A message encrypted under the public constant must not decrypt either
+        let (shared_secret, hybrid_ciphertext) = public_key.encapsulate().unwrap();
+        let message = encrypt0(&public_key, &shared_secret, &hybrid_ciphertext, b"secret firmware", b"", &mut OsRng);
+        assert!(cose_decrypt0(&no_schemes, &message, b"").is_err());
+    }
+}
@@ -1,17 +1,57 @@
-use crate::hybrid_signature::generate_combined_public_key;
-use crate::schemes::AlgorithmPurpose;
-
-pub fn hash_combined_public_key(combined_public_key: &[u8]) -> Vec<u8> {
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(&[
-        1 // = purpose number for hashing combined public keys
-    ]);
-    hasher.update(combined_public_key);
-    let result = hasher.finalize();
-    return result.as_bytes().to_vec();
-}
-
-pub fn generate_address(purpose: AlgorithmPurpose, seed: &[u8]) -> Vec<u8> {
-    let combined_public_key = generate_combined_public_key(purpose, seed);
-    return hash_combined_public_key(&combined_public_key);
+use crate::hybrid_signature::{generate_combined_public_key, KeyGenerationOptions};
+use crate::hybrid_signature::combined_public_key_header::{purpose_to_byte, COMBINED_PUBLIC_KEY_VERSION};
+use crate::hybrid_signature::read_combined_public_key_header;
+use crate::schemes::AlgorithmPurpose;
+use crate::varint::encode_varint;
+
+pub fn hash_combined_public_key(combined_public_key: &[u8]) -> Result<Vec<u8>, String> {
+    let (header, body) = read_combined_public_key_header(combined_public_key)?;
+    let mut hasher = blake3::Hasher::new();
+    match header {
+        // Keys with a version 2 header commit to their purpose, so the same bytes can't stand
+        // for a signature and a key encapsulation address at once
+        Some(header) if header.version == COMBINED_PUBLIC_KEY_VERSION => {
+            hasher.update(&[
+                4, // = purpose number for hashing combined public keys with a purpose
+                purpose_to_byte(header.purpose),
+            ]);
+        }
+        // Keys without header or with a version 1 header keep the address they always had
+        _ => {
+            hasher.update(&[
+                1 // = purpose number for hashing combined public keys
+            ]);
+        }
+    }
+    hasher.update(body);
+    let result = hasher.finalize();
+    return Ok(result.as_bytes().to_vec());
+}
+
+// Commits to a threshold and a set of signature public keys. Keys enter through their own
+// addresses, sorted, so neither headers nor the order the keys are listed in change the result.
+pub fn hash_multisig_public_keys(threshold: usize, combined_public_keys: &[&[u8]]) -> Result<Vec<u8>, String> {
+    let mut addresses = Vec::<Vec<u8>>::new();
+    for combined_public_key in combined_public_keys.iter() {
+        addresses.push(hash_combined_public_key(combined_public_key)?);
+    }
+    addresses.sort();
+    let mut counts = Vec::<u8>::new();
+    encode_varint(threshold as u64, &mut counts);
+    encode_varint(addresses.len() as u64, &mut counts);
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[
+        5 // = purpose number for hashing multisig public keys
+    ]);
+    hasher.update(&counts);
+    for address in addresses.iter() {
+        hasher.update(address);
+    }
+    let result = hasher.finalize();
+    return Ok(result.as_bytes().to_vec());
+}
+
+pub fn generate_address(purpose: AlgorithmPurpose, seed: &[u8]) -> Result<Vec<u8>, String> {
+    let combined_public_key = generate_combined_public_key(purpose, seed, KeyGenerationOptions::default())?;
+    return hash_combined_public_key(&combined_public_key);
 }
\ No newline at end of file
@@ -0,0 +1,229 @@
+use crate::hybrid_signature::{CombinedPublicKey, HybridSignature, HybridSigner, KeyEncapsulationPublicKey, KeyPurpose, SignatureAddress, SignaturePublicKey};
+use crate::varint::{decode_varint, encode_varint};
+
+// Compact alternative to X.509 for peer networks:
+// version || key usage || not before || not after || issuer address ||
+// signature public key || key encapsulation public key || signature
+// Times are big-endian Unix seconds, keys and the signature are prefixed with their varint
+// length and an absent key is encoded as an empty one. The signature covers everything before it,
+// prefixed with CERTIFICATE_SIGNING_CONTEXT.
+pub const CERTIFICATE_VERSION: u8 = 1;
+const CERTIFICATE_SIGNING_CONTEXT: &[u8] = b"hybrid certificate";
+const ADDRESS_LEN: usize = 32;
+
+// Key usage flags
+pub const KEY_USAGE_SIGN: u8 = 1;
+pub const KEY_USAGE_ENCAPSULATE: u8 = 2;
+pub const KEY_USAGE_CERTIFY: u8 = 4;
+const KEY_USAGE_ALL: u8 = KEY_USAGE_SIGN | KEY_USAGE_ENCAPSULATE | KEY_USAGE_CERTIFY;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HybridCertificate {
+    pub key_usage: u8,
+    pub not_before: u64,
+    pub not_after: u64,
+    pub issuer: SignatureAddress,
+    pub signature_public_key: Option<SignaturePublicKey>,
+    pub key_encapsulation_public_key: Option<KeyEncapsulationPublicKey>,
+    pub signature: HybridSignature,
+}
+
+fn push_length_prefixed(out: &mut Vec<u8>, bytes: &[u8]) {
+    encode_varint(bytes.len() as u64, out);
+    out.extend(bytes);
+}
+
+fn read_length_prefixed<'a>(bytes: &'a [u8], idx: &mut usize) -> Result<&'a [u8], String> {
+    let len = decode_varint(bytes, idx)?;
+    if len > (bytes.len() - *idx) as u64 {
+        return Err("Certificate is truncated".to_string());
+    }
+    let field = &bytes[*idx..*idx + len as usize];
+    *idx += len as usize;
+    return Ok(field);
+}
+
+fn read_array<'a>(bytes: &'a [u8], idx: &mut usize, len: usize) -> Result<&'a [u8], String> {
+    return match bytes.get(*idx..*idx + len) {
+        Some(field) => {
+            *idx += len;
+            Ok(field)
+        }
+        None => Err("Certificate is truncated".to_string()),
+    };
+}
+
+fn read_optional_key<P: KeyPurpose>(bytes: &[u8], idx: &mut usize) -> Result<Option<CombinedPublicKey<P>>, String> {
+    let key = read_length_prefixed(bytes, idx)?;
+    if key.is_empty() {
+        return Ok(None);
+    }
+    return Ok(Some(CombinedPublicKey::from_bytes(key)?));
+}
+
+// Usage flags have to be backed by a key of the right purpose
+fn check_key_usage(key_usage: u8, signature_public_key: &Option<SignaturePublicKey>, key_encapsulation_public_key: &Option<KeyEncapsulationPublicKey>) -> Result<(), String> {
+    if key_usage & !KEY_USAGE_ALL != 0 {
+        return Err(format!("Unknown key usage flags {:#04x}", key_usage & !KEY_USAGE_ALL));
+    }
+    if signature_public_key.is_none() && key_encapsulation_public_key.is_none() {
+        return Err("Certificate has no subject public key".to_string());
+    }
+    // A key without components is useless, and without header it encodes like an absent one
+    let signature_key_is_empty = matches!(signature_public_key, Some(pk) if pk.parse().components.is_empty());
+    let key_encapsulation_key_is_empty = matches!(key_encapsulation_public_key, Some(pk) if pk.parse().components.is_empty());
+    if signature_key_is_empty || key_encapsulation_key_is_empty {
+        return Err("Subject public keys need at least one component".to_string());
+    }
+    if key_usage & (KEY_USAGE_SIGN | KEY_USAGE_CERTIFY) != 0 && signature_public_key.is_none() {
+        return Err("Signing and certifying need a signature public key".to_string());
+    }
+    if key_usage & KEY_USAGE_ENCAPSULATE != 0 && key_encapsulation_public_key.is_none() {
+        return Err("Encapsulating needs a key encapsulation public key".to_string());
+    }
+    return Ok(());
+}
+
+fn to_be_signed(
+    key_usage: u8,
+    not_before: u64,
+    not_after: u64,
+    issuer: &SignatureAddress,
+    signature_public_key: &Option<SignaturePublicKey>,
+    key_encapsulation_public_key: &Option<KeyEncapsulationPublicKey>,
+) -> Vec<u8> {
+    let mut out = vec![CERTIFICATE_VERSION, key_usage];
+    out.extend(not_before.to_be_bytes());
+    out.extend(not_after.to_be_bytes());
+    out.extend(issuer.as_bytes());
+    push_length_prefixed(&mut out, signature_public_key.as_ref().map_or(&[][..], |pk| pk.as_bytes()));
+    push_length_prefixed(&mut out, key_encapsulation_public_key.as_ref().map_or(&[][..], |pk| pk.as_bytes()));
+    return out;
+}
+
+fn signing_input(to_be_signed: &[u8]) -> Vec<u8> {
+    return [CERTIFICATE_SIGNING_CONTEXT, to_be_signed].concat();
+}
+
+impl HybridCertificate {
+    pub fn issue<S: HybridSigner>(
+        issuer: &S,
+        signature_public_key: Option<SignaturePublicKey>,
+        key_encapsulation_public_key: Option<KeyEncapsulationPublicKey>,
+        not_before: u64,
+        not_after: u64,
+        key_usage: u8,
+    ) -> Result<Self, String> {
+        check_key_usage(key_usage, &signature_public_key, &key_encapsulation_public_key)?;
+        if not_before > not_after {
+            return Err("Certificate validity ends before it starts".to_string());
+        }
+        let issuer_address = issuer.signature_public_key()?.address();
+        let to_be_signed = to_be_signed(key_usage, not_before, not_after, &issuer_address, &signature_public_key, &key_encapsulation_public_key);
+        return Ok(HybridCertificate {
+            key_usage,
+            not_before,
+            not_after,
+            issuer: issuer_address,
+            signature_public_key,
+            key_encapsulation_public_key,
+            signature: issuer.sign(&signing_input(&to_be_signed))?,
+        });
+    }
+
+    // Root certificate for the signer's own signature key
+    pub fn self_signed<S: HybridSigner>(signer: &S, not_before: u64, not_after: u64, key_usage: u8) -> Result<Self, String> {
+        return HybridCertificate::issue(signer, Some(signer.signature_public_key()?), None, not_before, not_after, key_usage);
+    }
+
+    fn to_be_signed(&self) -> Vec<u8> {
+        return to_be_signed(self.key_usage, self.not_before, self.not_after, &self.issuer, &self.signature_public_key, &self.key_encapsulation_public_key);
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.to_be_signed();
+        push_length_prefixed(&mut out, self.signature.as_bytes());
+        return out;
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut idx: usize = 0;
+        let header = read_array(bytes, &mut idx, 2)?;
+        if header[0] != CERTIFICATE_VERSION {
+            return Err(format!("Unsupported certificate version {}", header[0]));
+        }
+        let key_usage = header[1];
+        let not_before = u64::from_be_bytes(read_array(bytes, &mut idx, 8)?.try_into().expect("Read 8 bytes"));
+        let not_after = u64::from_be_bytes(read_array(bytes, &mut idx, 8)?.try_into().expect("Read 8 bytes"));
+        let issuer = SignatureAddress::from_bytes(read_array(bytes, &mut idx, ADDRESS_LEN)?)?;
+        let signature_public_key = read_optional_key(bytes, &mut idx)?;
+        let key_encapsulation_public_key = read_optional_key(bytes, &mut idx)?;
+        let signature = HybridSignature::from_bytes(read_length_prefixed(bytes, &mut idx)?)?;
+        if idx != bytes.len() {
+            return Err("Certificate has trailing bytes".to_string());
+        }
+        check_key_usage(key_usage, &signature_public_key, &key_encapsulation_public_key)?;
+        return Ok(HybridCertificate {
+            key_usage,
+            not_before,
+            not_after,
+            issuer,
+            signature_public_key,
+            key_encapsulation_public_key,
+            signature,
+        });
+    }
+
+    // Address of the subject's signature key, which is what certificates it issues name as issuer
+    pub fn subject_address(&self) -> Option<SignatureAddress> {
+        return self.signature_public_key.as_ref().map(|pk| pk.address());
+    }
+
+    pub fn has_key_usage(&self, key_usage: u8) -> bool {
+        return self.key_usage & key_usage == key_usage;
+    }
+
+    pub fn is_valid_at(&self, unix_seconds: u64) -> bool {
+        return self.not_before <= unix_seconds && unix_seconds <= self.not_after;
+    }
+
+    // Checks that the issuer key matches the issuer address and that all its components signed
+    pub fn is_signed_by(&self, issuer: &SignaturePublicKey) -> bool {
+        return issuer.address() == self.issuer && issuer.verify(&signing_input(&self.to_be_signed()), &self.signature);
+    }
+}
+
+// Walks a chain given leaf first. Every certificate has to be valid at the given time and
+// signed by the next one, which has to be allowed to certify. The last one has to be a
+// self-signed root whose address is trusted.
+pub fn verify_hybrid_certificate_chain(chain: &[HybridCertificate], trusted_roots: &[SignatureAddress], unix_seconds: u64) -> Result<(), String> {
+    let root = match chain.last() {
+        Some(root) => root,
+        None => return Err("Certificate chain is empty".to_string()),
+    };
+    for (idx, certificate) in chain.iter().enumerate() {
+        if !certificate.is_valid_at(unix_seconds) {
+            return Err(format!("Certificate {} is not valid at {}", idx, unix_seconds));
+        }
+        // The root signs itself
+        let issuer = chain.get(idx + 1).unwrap_or(root);
+        if idx + 1 < chain.len() && !issuer.has_key_usage(KEY_USAGE_CERTIFY) {
+            return Err(format!("Certificate {} may not certify other keys", idx + 1));
+        }
+        let issuer_public_key = match &issuer.signature_public_key {
+            Some(issuer_public_key) => issuer_public_key,
+            None => return Err(format!("Issuer of certificate {} has no signature public key", idx)),
+        };
+        if !certificate.is_signed_by(issuer_public_key) {
+            return Err(format!("Certificate {} is not signed by its issuer", idx));
+        }
+    }
+    match root.subject_address() {
+        Some(root_address) if trusted_roots.contains(&root_address) => {}
+        _ => return Err("Certificate chain does not end in a trusted root".to_string()),
+    }
+    if chain.len() > 1 && !root.has_key_usage(KEY_USAGE_CERTIFY) {
+        return Err("Root certificate may not certify other keys".to_string());
+    }
+    return Ok(());
+}
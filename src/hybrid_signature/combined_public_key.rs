@@ -1,62 +1,108 @@
-use crate::hybrid_signature::seed_branch::create_scheme_seed_branch;
-use crate::hybrid_signature::seed_parser::parse_seed;
-use crate::schemes::{signature, key_encapsulation};
-use crate::schemes::{AlgorithmPurpose, SizeKind, SchemeInfo};
-
-fn add_keyair(public_keys: &mut Vec<u8>, public_key: &[u8], scheme_id: u8, scheme_config_id: u8, scheme_info: SchemeInfo) {
-    // Start by pushing the scheme id and configuration
-    public_keys.push(scheme_id);
-    public_keys.push(scheme_config_id);
-
-    if matches!(scheme_info.pk_size_info.kind, SizeKind::VariableSized) {
-        // Add byte length if nessecary
-        let le_bytes = &public_key.len().to_le_bytes();
-        let slice_end = scheme_info.pk_size_info.variable_size_bytelen.unwrap() as usize;
-        public_keys.extend(&le_bytes[..slice_end]);
-    }
-    public_keys.extend(public_key);
-}
-
-pub fn generate_combined_public_key(purpose: AlgorithmPurpose, seed: &[u8]) -> Vec<u8> {
-    let parsed_seed = parse_seed(&seed);
-    let mut public_keys = Vec::<u8>::new();
-    match purpose {
-        AlgorithmPurpose::Signature => {
-            let mapping = signature::get_id_to_ref_mapping();
-            for scheme_id in parsed_seed.signature_scheme_ids.iter() {
-                let scheme_impl = mapping.get(scheme_id).expect(
-                    format!(
-                        "Algorithm not with id {} and config {} not found!",
-                        scheme_id.0, scheme_id.1
-                    )
-                    .as_str(),
-                );
-                let seed_branch = create_scheme_seed_branch(&parsed_seed.seed, AlgorithmPurpose::Signature, scheme_id.0, scheme_id.1);
-                // Generate keypair
-                let keypair = scheme_impl.generate_keypair(&seed_branch);
-                // Add size data
-                let scheme_info = scheme_impl.get_scheme_info();
-                add_keyair(&mut public_keys, &keypair.pk, scheme_id.0, scheme_id.1, scheme_info);
-            }
-        }
-        AlgorithmPurpose::KeyEncapsulation => {
-            let mapping = key_encapsulation::get_id_to_ref_mapping();
-            for scheme_id in parsed_seed.key_encapsulation_scheme_ids.iter() {
-                let scheme_impl = mapping.get(scheme_id).expect(
-                    format!(
-                        "Algorithm not with id {} and config {} not found!",
-                        scheme_id.0, scheme_id.1
-                    )
-                    .as_str(),
-                );
-                let seed_branch = create_scheme_seed_branch(&parsed_seed.seed, AlgorithmPurpose::KeyEncapsulation, scheme_id.0, scheme_id.1);
-                // Generate keypair
-                let keypair = scheme_impl.generate_keypair(&seed_branch);
-                // Add size data
-                let scheme_info = scheme_impl.get_scheme_info();
-                add_keyair(&mut public_keys, &keypair.pk, scheme_id.0, scheme_id.1, scheme_info);
-            }
-        }
-    }
-    return public_keys;
-}
+use crate::hybrid_signature::combined_public_key_header::push_combined_public_key_header;
+use crate::hybrid_signature::seed_parser::parse_seed;
+use crate::hybrid_signature::ComponentEncoding;
+use crate::scheme_info_mapping::get_id_to_info_mapping;
+use crate::schemes::{signature, key_encapsulation};
+use crate::schemes::{AlgorithmPurpose, SchemeInfo};
+use crate::hybrid_signature::sized_field::encode_sized_field;
+use crate::self_test::{key_encapsulation_pairwise_consistency_test, signature_pairwise_consistency_test};
+
+pub(crate) fn add_keyair(public_keys: &mut Vec<u8>, encoding: ComponentEncoding, public_key: &[u8], scheme_id: u16, scheme_config_id: u16, scheme_info: &SchemeInfo) -> Result<(), String> {
+    // Start by pushing the scheme id and configuration
+    encoding.encode_scheme_id((scheme_id, scheme_config_id), public_keys)?;
+    return match encode_sized_field(public_keys, encoding, public_key, &scheme_info.pk_size_info) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!(
+            "Public key of algorithm with id {} and config {} can't be encoded: {}",
+            scheme_id, scheme_config_id, e
+        )),
+    };
+}
+
+// Encodes components in the given order, which has to be sorted by scheme id for varint encoded
+// keys. Those get a version 2 header, without it they would be taken for legacy keys.
+pub(crate) fn encode_combined_public_key(purpose: AlgorithmPurpose, encoding: ComponentEncoding, components: &[((u16, u16), &[u8])]) -> Result<Vec<u8>, String> {
+    let mapping = get_id_to_info_mapping(purpose);
+    let mut public_keys = Vec::<u8>::new();
+    if encoding == ComponentEncoding::Varint {
+        if !components.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return Err("Components of version 2 combined public keys must be sorted by scheme id".to_string());
+        }
+        push_combined_public_key_header(&mut public_keys, purpose, encoding, components.len())?;
+    }
+    for (scheme_id, pk) in components.iter() {
+        let scheme_info = match mapping.get(scheme_id) {
+            Some(scheme_info) => scheme_info,
+            None => {
+                return Err(format!(
+                    "Algorithm with id {} and config {} not found!",
+                    scheme_id.0, scheme_id.1
+                ));
+            }
+        };
+        add_keyair(&mut public_keys, encoding, pk, scheme_id.0, scheme_id.1, scheme_info)?;
+    }
+    return Ok(public_keys);
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyGenerationOptions {
+    // Runs a pairwise consistency test (sign/verify or encapsulate/decapsulate) on every generated
+    // keypair, so key generation fails closed if a backend misbehaves
+    pub pairwise_consistency_test: bool,
+}
+
+pub fn generate_combined_public_key(purpose: AlgorithmPurpose, seed: &[u8], options: KeyGenerationOptions) -> Result<Vec<u8>, String> {
+    let parsed_seed = parse_seed(&seed)?;
+    let mut components = Vec::<((u16, u16), Vec<u8>)>::new();
+    match purpose {
+        AlgorithmPurpose::Signature => {
+            let mapping = signature::get_id_to_ref_mapping();
+            for scheme_id in parsed_seed.signature_scheme_ids.iter() {
+                let scheme_impl = match mapping.get(scheme_id) {
+                    Some(scheme_impl) => scheme_impl,
+                    None => {
+                        return Err(format!(
+                            "Algorithm not with id {} and config {} not found!",
+                            scheme_id.0, scheme_id.1
+                        ));
+                    }
+                };
+                let seed_branch = parsed_seed.scheme_seed_branch(AlgorithmPurpose::Signature, *scheme_id)?;
+                // Generate keypair
+                let keypair = scheme_impl.generate_keypair(&seed_branch);
+                if options.pairwise_consistency_test {
+                    signature_pairwise_consistency_test(scheme_impl.as_ref(), &keypair)?;
+                }
+                components.push((*scheme_id, keypair.pk.clone()));
+            }
+        }
+        AlgorithmPurpose::KeyEncapsulation => {
+            let mapping = key_encapsulation::get_id_to_ref_mapping();
+            for scheme_id in parsed_seed.key_encapsulation_scheme_ids.iter() {
+                let scheme_impl = match mapping.get(scheme_id) {
+                    Some(scheme_impl) => scheme_impl,
+                    None => {
+                        return Err(format!(
+                            "Algorithm not with id {} and config {} not found!",
+                            scheme_id.0, scheme_id.1
+                        ));
+                    }
+                };
+                let seed_branch = parsed_seed.scheme_seed_branch(AlgorithmPurpose::KeyEncapsulation, *scheme_id)?;
+                // Generate keypair
+                let keypair = scheme_impl.generate_keypair(&seed_branch);
+                if options.pairwise_consistency_test {
+                    key_encapsulation_pairwise_consistency_test(scheme_impl.as_ref(), &keypair)?;
+                }
+                components.push((*scheme_id, keypair.pk.clone()));
+            }
+        }
+    }
+    // Legacy keys list their components in seed order, keys of current seeds sorted by scheme id
+    if parsed_seed.encoding() == ComponentEncoding::Varint {
+        components.sort_by_key(|(scheme_id, _)| *scheme_id);
+    }
+    let components: Vec<((u16, u16), &[u8])> = components.iter().map(|(scheme_id, pk)| (*scheme_id, pk.as_slice())).collect();
+    return encode_combined_public_key(purpose, parsed_seed.encoding(), &components);
+}
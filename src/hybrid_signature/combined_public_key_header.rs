@@ -0,0 +1,137 @@
+use crate::hybrid_signature::combined_public_key::{generate_combined_public_key, KeyGenerationOptions};
+use crate::hybrid_signature::combined_public_key_parser::{parse_combined_public_key, ParsedCombinedPublicKey};
+use crate::hybrid_signature::ComponentEncoding;
+use crate::schemes::AlgorithmPurpose;
+use crate::varint::{decode_varint, encode_varint};
+use std::convert::TryInto;
+
+// Optional header in front of a combined public key:
+// magic || version || purpose || component count
+// Version 1 headers have a single byte count and a body in the legacy encoding, like keys
+// without header. Version 2 headers have a varint count and a body in the varint encoding.
+pub const COMBINED_PUBLIC_KEY_MAGIC: [u8; 3] = *b"HYK";
+pub const COMBINED_PUBLIC_KEY_VERSION: u8 = 2;
+pub const COMBINED_PUBLIC_KEY_VERSION_SINGLE_BYTE_COUNT: u8 = 1;
+// Length of a header whose component count fits in one byte, which is also the shortest header
+pub const COMBINED_PUBLIC_KEY_HEADER_LEN: usize = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CombinedPublicKeyHeader {
+    pub version: u8,
+    pub purpose: AlgorithmPurpose,
+    pub component_count: u32,
+}
+
+impl CombinedPublicKeyHeader {
+    pub fn encoding(&self) -> ComponentEncoding {
+        if self.version == COMBINED_PUBLIC_KEY_VERSION {
+            return ComponentEncoding::Varint;
+        }
+        return ComponentEncoding::Legacy;
+    }
+}
+
+pub(crate) fn purpose_to_byte(purpose: AlgorithmPurpose) -> u8 {
+    return match purpose {
+        AlgorithmPurpose::Signature => 0,
+        AlgorithmPurpose::KeyEncapsulation => 1,
+    };
+}
+
+fn purpose_from_byte(byte: u8) -> Result<AlgorithmPurpose, String> {
+    return match byte {
+        0 => Ok(AlgorithmPurpose::Signature),
+        1 => Ok(AlgorithmPurpose::KeyEncapsulation),
+        _ => Err(format!("Unknown purpose {} in combined public key header", byte)),
+    };
+}
+
+// Splits off the header, if the key has one
+pub fn read_combined_public_key_header(combined_public_key: &[u8]) -> Result<(Option<CombinedPublicKeyHeader>, &[u8]), String> {
+    if !combined_public_key.starts_with(&COMBINED_PUBLIC_KEY_MAGIC) {
+        return Ok((None, combined_public_key));
+    }
+    if combined_public_key.len() < COMBINED_PUBLIC_KEY_HEADER_LEN {
+        return Err("Combined public key header is truncated".to_string());
+    }
+    let version = combined_public_key[3];
+    let purpose = purpose_from_byte(combined_public_key[4])?;
+    let mut idx = COMBINED_PUBLIC_KEY_MAGIC.len() + 2;
+    let component_count = match version {
+        COMBINED_PUBLIC_KEY_VERSION => {
+            let component_count = decode_varint(combined_public_key, &mut idx)?;
+            match component_count.try_into() {
+                Ok(component_count) => component_count,
+                Err(_) => return Err("Combined public key header announces too many components".to_string()),
+            }
+        }
+        COMBINED_PUBLIC_KEY_VERSION_SINGLE_BYTE_COUNT => {
+            let component_count = match combined_public_key.get(idx) {
+                Some(component_count) => *component_count as u32,
+                None => return Err("Combined public key header is truncated".to_string()),
+            };
+            idx += 1;
+            component_count
+        }
+        _ => return Err(format!("Unsupported combined public key version {}", version)),
+    };
+    let header = CombinedPublicKeyHeader {
+        version,
+        purpose,
+        component_count,
+    };
+    return Ok((Some(header), &combined_public_key[idx..]));
+}
+
+pub(crate) fn push_combined_public_key_header(out: &mut Vec<u8>, purpose: AlgorithmPurpose, encoding: ComponentEncoding, component_count: usize) -> Result<(), String> {
+    out.extend(COMBINED_PUBLIC_KEY_MAGIC);
+    match encoding {
+        ComponentEncoding::Legacy => {
+            let component_count = match u8::try_from(component_count) {
+                Ok(component_count) => component_count,
+                Err(_) => return Err("Combined public key has too many components for a version 1 header".to_string()),
+            };
+            out.push(COMBINED_PUBLIC_KEY_VERSION_SINGLE_BYTE_COUNT);
+            out.push(purpose_to_byte(purpose));
+            out.push(component_count);
+        }
+        ComponentEncoding::Varint => {
+            out.push(COMBINED_PUBLIC_KEY_VERSION);
+            out.push(purpose_to_byte(purpose));
+            encode_varint(component_count as u64, out);
+        }
+    }
+    return Ok(());
+}
+
+// Keys without header are in the legacy encoding, so they get a version 1 header
+pub fn add_combined_public_key_header(purpose: AlgorithmPurpose, combined_public_key: &[u8]) -> Result<Vec<u8>, String> {
+    let (header, body) = read_combined_public_key_header(combined_public_key)?;
+    if header.is_some() {
+        return Err("Combined public key already has a header".to_string());
+    }
+    let parsed = parse_combined_public_key(purpose, body)?;
+    let mut result = Vec::new();
+    push_combined_public_key_header(&mut result, purpose, ComponentEncoding::Legacy, parsed.component_count())?;
+    result.extend(body);
+    return Ok(result);
+}
+
+// Keys of current seeds carry a header already, keys of older seeds get a version 1 header
+pub fn generate_combined_public_key_with_header(purpose: AlgorithmPurpose, seed: &[u8]) -> Result<Vec<u8>, String> {
+    let combined_public_key = generate_combined_public_key(purpose, seed, KeyGenerationOptions::default())?;
+    if read_combined_public_key_header(&combined_public_key)?.0.is_some() {
+        return Ok(combined_public_key);
+    }
+    return add_combined_public_key_header(purpose, &combined_public_key);
+}
+
+// Parses a combined public key that carries a header, taking the purpose from it
+pub fn parse_self_describing_combined_public_key(combined_public_key: &[u8]) -> Result<(AlgorithmPurpose, ParsedCombinedPublicKey), String> {
+    let purpose = match read_combined_public_key_header(combined_public_key)? {
+        (Some(header), _) => header.purpose,
+        (None, _) => return Err("Combined public key has no header".to_string()),
+    };
+    let parsed = parse_combined_public_key(purpose, combined_public_key)?;
+    return Ok((purpose, parsed));
+}
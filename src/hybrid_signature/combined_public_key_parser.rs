@@ -1,67 +1,146 @@
-use std::convert::TryInto;
-use std::collections::HashMap;
-use std::mem;
-use crate::schemes::SizeKind;
-use crate::schemes::AlgorithmPurpose;
-use crate::scheme_info_mapping::get_id_to_info_mapping;
-
-pub struct ParsedCombinedPublicKey {
-    pub id_mapping: HashMap<(u8, u8), Vec<u8>>
-}
-
-pub fn parse_combined_public_key(purpose: AlgorithmPurpose, combined_public_key: &[u8]) -> Result<ParsedCombinedPublicKey, String> {
-    let mapping = get_id_to_info_mapping(purpose);
-    let mut idx: usize = 0;
-    let mut id_mapping: HashMap<(u8, u8), Vec<u8>> = HashMap::new();
-    while idx < combined_public_key.len() {
-        let scheme_id = (combined_public_key[idx], combined_public_key[idx + 1]);
-        let scheme_info = match mapping.get(&scheme_id) {
-            Some(result) => {
-                result
-            }
-            None => {
-                return Err(format!(
-                    "Algorithm with id {} and config {} not found!",
-                    scheme_id.0, scheme_id.1
-                ));
-            }
-        };
-        idx += 2;
-        let mut pk_length: usize = 0;
-        if matches!(scheme_info.pk_size_info.kind, SizeKind::VariableSized) {
-            let pk_len_byte_size = scheme_info
-                .pk_size_info
-                .variable_size_bytelen
-                .unwrap() as usize;
-            let slice = match combined_public_key.get(idx..idx + pk_len_byte_size) {
-                Some(slice) => {
-                    slice
-                }
-                None => {
-                    return Err("combined_public_key index out of bounds".to_string());
-                }
-            };
-            idx += pk_len_byte_size;
-            let mut vec_bytes = slice.to_vec();
-            vec_bytes.resize(mem::size_of::<usize>(), 0u8);
-            pk_length = usize::from_le_bytes(vec_bytes[..].try_into().unwrap());
-        }
-        else if matches!(scheme_info.pk_size_info.kind, SizeKind::FixedSized) {
-            pk_length = scheme_info.pk_size_info.fixed_size.expect(format!("fixed_size for algorithm with id {} and config {} None while FixedSized is defined!", scheme_id.0, scheme_id.1).as_str()) as usize;
-        }
-
-        let pk = match combined_public_key.get(idx..idx + pk_length) {
-            Some(slice) => {
-                slice
-            }
-            None => {
-                return Err("combined_public_key index out of bounds".to_string());
-            }
-        };
-        id_mapping.insert(scheme_id, pk.to_vec());
-        idx += pk_length;
-    }
-    return Ok(ParsedCombinedPublicKey {
-        id_mapping
-    });
-}
+use std::collections::HashMap;
+use crate::schemes::AlgorithmPurpose;
+use crate::scheme_info_mapping::get_id_to_info_mapping;
+use crate::hybrid_signature::combined_public_key::encode_combined_public_key;
+use crate::hybrid_signature::combined_public_key_header::read_combined_public_key_header;
+use crate::hybrid_signature::sized_field::decode_sized_field;
+use crate::hybrid_signature::ComponentEncoding;
+
+pub struct ParsedCombinedPublicKey {
+    pub purpose: AlgorithmPurpose,
+    // Components in the order they appear in the encoding
+    pub components: Vec<((u16, u16), Vec<u8>)>,
+    pub encoding: ComponentEncoding,
+    // Public keys by single byte scheme id, as parsed keys held them before components kept
+    // their order. Filled by new(), components with wider ids are left out.
+    #[deprecated(note = "use components or get(), which keep the order and wide scheme ids")]
+    pub id_mapping: HashMap<(u8, u8), Vec<u8>>,
+}
+
+impl ParsedCombinedPublicKey {
+    #[allow(deprecated)]
+    pub fn new(purpose: AlgorithmPurpose, encoding: ComponentEncoding, components: Vec<((u16, u16), Vec<u8>)>) -> Self {
+        let mut id_mapping = HashMap::new();
+        for (scheme_id, pk) in components.iter() {
+            if let (Ok(id), Ok(config_id)) = (u8::try_from(scheme_id.0), u8::try_from(scheme_id.1)) {
+                id_mapping.insert((id, config_id), pk.clone());
+            }
+        }
+        return ParsedCombinedPublicKey {
+            purpose,
+            components,
+            encoding,
+            id_mapping,
+        };
+    }
+
+    pub fn get(&self, scheme_id: &(u16, u16)) -> Option<&[u8]> {
+        return self
+            .components
+            .iter()
+            .find(|(id, _)| id == scheme_id)
+            .map(|(_, pk)| pk.as_slice());
+    }
+
+    pub fn component_count(&self) -> usize {
+        return self.components.len();
+    }
+
+    pub fn scheme_ids(&self) -> Vec<(u16, u16)> {
+        return self.components.iter().map(|(id, _)| *id).collect();
+    }
+
+    // Encodes the components again in their current order. Legacy keys are encoded without
+    // header, varint keys with the version 2 header they need.
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let components: Vec<((u16, u16), &[u8])> = self.components.iter().map(|(scheme_id, pk)| (*scheme_id, pk.as_slice())).collect();
+        return encode_combined_public_key(self.purpose, self.encoding, &components);
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        return self.components.windows(2).all(|pair| pair[0].0 < pair[1].0);
+    }
+}
+
+pub fn parse_combined_public_key(purpose: AlgorithmPurpose, combined_public_key: &[u8]) -> Result<ParsedCombinedPublicKey, String> {
+    let (header, combined_public_key) = read_combined_public_key_header(combined_public_key)?;
+    if let Some(header) = header {
+        if header.purpose != purpose {
+            return Err(format!("Combined public key is for {:?}, not {:?}", header.purpose, purpose));
+        }
+    }
+    let encoding = match header {
+        Some(header) => header.encoding(),
+        None => ComponentEncoding::Legacy,
+    };
+    let mapping = get_id_to_info_mapping(purpose);
+    let mut idx: usize = 0;
+    let mut components: Vec<((u16, u16), Vec<u8>)> = Vec::new();
+    while idx < combined_public_key.len() {
+        let scheme_id = encoding.decode_scheme_id(combined_public_key, &mut idx)?;
+        let scheme_info = match mapping.get(&scheme_id) {
+            Some(result) => {
+                result
+            }
+            None => {
+                return Err(format!(
+                    "Algorithm with id {} and config {} not found!",
+                    scheme_id.0, scheme_id.1
+                ));
+            }
+        };
+        if components.iter().any(|(id, _)| *id == scheme_id) {
+            return Err(format!(
+                "Algorithm with id {} and config {} appears more than once!",
+                scheme_id.0, scheme_id.1
+            ));
+        }
+        // Legacy keys keep the scheme order of their seed, varint keys are sorted
+        if encoding == ComponentEncoding::Varint && components.last().is_some_and(|(id, _)| *id > scheme_id) {
+            return Err("Components of version 2 combined public keys must be sorted by scheme id".to_string());
+        }
+        let pk = match decode_sized_field(combined_public_key, &mut idx, encoding, &scheme_info.pk_size_info) {
+            Ok(pk) => pk,
+            Err(e) => {
+                return Err(format!(
+                    "Public key of algorithm with id {} and config {} is malformed: {}",
+                    scheme_id.0, scheme_id.1, e
+                ));
+            }
+        };
+        components.push((scheme_id, pk.to_vec()));
+    }
+    if let Some(header) = header {
+        if header.component_count as usize != components.len() {
+            return Err(format!(
+                "Combined public key header announces {} components but {} were found",
+                header.component_count, components.len()
+            ));
+        }
+    }
+    return Ok(ParsedCombinedPublicKey::new(purpose, encoding, components));
+}
+
+// Like parse_combined_public_key, but only accepts the one canonical encoding of a key:
+// components sorted by scheme id, no duplicates and nothing that re-encodes differently.
+// Legacy keys of seeds that don't list their schemes sorted are valid, but not canonical.
+pub fn parse_canonical_combined_public_key(purpose: AlgorithmPurpose, combined_public_key: &[u8]) -> Result<ParsedCombinedPublicKey, String> {
+    let parsed = parse_combined_public_key(purpose, combined_public_key)?;
+    if !parsed.is_sorted() {
+        return Err("Combined public key components are not sorted by scheme id".to_string());
+    }
+    // Legacy keys may have a version 1 header, which encode() leaves out
+    let (_, body) = read_combined_public_key_header(combined_public_key)?;
+    let expected = match parsed.encoding {
+        ComponentEncoding::Legacy => body,
+        ComponentEncoding::Varint => combined_public_key,
+    };
+    if parsed.encode()? != expected {
+        return Err("Combined public key is not minimally encoded".to_string());
+    }
+    return Ok(parsed);
+}
+
+pub fn is_canonical_combined_public_key(purpose: AlgorithmPurpose, combined_public_key: &[u8]) -> bool {
+    return parse_canonical_combined_public_key(purpose, combined_public_key).is_ok();
+}
@@ -0,0 +1,76 @@
+use crate::varint::{decode_scheme_id, encode_scheme_id};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Layout of the components of combined public keys, signatures and ciphertexts.
+// Legacy is the original layout with single byte scheme ids, used by keys without header or
+// with a version 1 header and by seeds up to version 2. Varint has varint scheme ids and is used
+// by keys with a version 2 header and by seeds from version 3 on. Signatures and ciphertexts
+// follow the encoding of the key or seed that made them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ComponentEncoding {
+    Legacy,
+    #[default]
+    Varint,
+}
+
+impl ComponentEncoding {
+    pub(crate) fn encode_scheme_id(self, scheme_id: (u16, u16), out: &mut Vec<u8>) -> Result<(), String> {
+        match self {
+            ComponentEncoding::Legacy => {
+                let (id, config_id) = match (u8::try_from(scheme_id.0), u8::try_from(scheme_id.1)) {
+                    (Ok(id), Ok(config_id)) => (id, config_id),
+                    _ => {
+                        return Err(format!(
+                            "Algorithm with id {} and config {} doesn't fit the legacy encoding",
+                            scheme_id.0, scheme_id.1
+                        ));
+                    }
+                };
+                out.push(id);
+                out.push(config_id);
+            }
+            ComponentEncoding::Varint => encode_scheme_id(scheme_id, out),
+        }
+        return Ok(());
+    }
+
+    pub(crate) fn decode_scheme_id(self, bytes: &[u8], idx: &mut usize) -> Result<(u16, u16), String> {
+        return match self {
+            ComponentEncoding::Legacy => match bytes.get(*idx..*idx + 2) {
+                Some(id) => {
+                    *idx += 2;
+                    Ok((id[0] as u16, id[1] as u16))
+                }
+                None => Err("Scheme id is truncated".to_string()),
+            },
+            ComponentEncoding::Varint => decode_scheme_id(bytes, idx),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheme_id_encodings() {
+        for (scheme_id, legacy, varint) in [
+            ((1, 0), vec![1, 0], vec![1, 0]),
+            ((200, 1), vec![200, 1], vec![0xc8, 0x01, 0x01]),
+            ((255, 255), vec![255, 255], vec![0xff, 0x01, 0xff, 0x01]),
+        ] {
+            for (encoding, expected) in [(ComponentEncoding::Legacy, legacy), (ComponentEncoding::Varint, varint)] {
+                let mut encoded = Vec::new();
+                encoding.encode_scheme_id(scheme_id, &mut encoded).unwrap();
+                assert_eq!(encoded, expected);
+                let mut idx = 0;
+                assert_eq!(encoding.decode_scheme_id(&encoded, &mut idx), Ok(scheme_id));
+                assert_eq!(idx, encoded.len());
+            }
+        }
+        assert!(ComponentEncoding::Legacy.encode_scheme_id((256, 0), &mut Vec::new()).is_err());
+        assert!(ComponentEncoding::Legacy.decode_scheme_id(&[1], &mut 0).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::hybrid_signature::combined_public_key::{add_keyair, encode_combined_public_key};
+use crate::hybrid_signature::key_encapsulation::decapsulate_with_secret_keys;
+use crate::hybrid_signature::signature::sign_message_with_keypairs;
+use crate::hybrid_signature::sized_field::check_field_size;
+use crate::hybrid_signature::{parse_seed, CombinedPublicKey, ComponentEncoding, HybridCiphertext, HybridDecapsulator, HybridSeed, HybridSignature, HybridSigner, KeyEncapsulationPublicKey, KeyPurpose, SharedSecret, SignaturePublicKey};
+use crate::schemes::{key_encapsulation, signature, AlgorithmPurpose, Keypair};
+use crate::self_test::{key_encapsulation_pairwise_consistency_test, signature_pairwise_consistency_test};
+
+// Component keypairs supplied one by one instead of derived from a single seed, e.g. to pair an
+// existing Ed25519 identity with a fresh post-quantum key. The combined public keys are encoded
+// exactly like the ones generated from a seed: new bundles use varint ids and keep components
+// sorted by scheme id, bundles derived from a legacy seed keep its encoding and scheme order.
+#[derive(Debug, Default)]
+pub struct HybridKeyBundle {
+    encoding: ComponentEncoding,
+    signature_keypairs: Vec<((u16, u16), Keypair)>,
+    key_encapsulation_keypairs: Vec<((u16, u16), Keypair)>,
+}
+
+fn insert_keypair(keypairs: &mut Vec<((u16, u16), Keypair)>, encoding: ComponentEncoding, scheme_id: (u16, u16), keypair: Keypair, name: &str) -> Result<(), String> {
+    if keypairs.iter().any(|(id, _)| *id == scheme_id) {
+        return Err(format!("Key bundle already has a {} keypair", name));
+    }
+    // Legacy keys keep the order of their seed, later keypairs go last
+    let idx = match encoding {
+        ComponentEncoding::Legacy => keypairs.len(),
+        ComponentEncoding::Varint => keypairs.partition_point(|(id, _)| *id < scheme_id),
+    };
+    keypairs.insert(idx, (scheme_id, keypair));
+    return Ok(());
+}
+
+impl HybridKeyBundle {
+    pub fn new() -> Self {
+        return HybridKeyBundle::default();
+    }
+
+    // Derives the same component keypairs the seed itself uses
+    pub fn from_seed(seed: &HybridSeed) -> Result<Self, String> {
+        let parsed_seed = parse_seed(seed.as_bytes())?;
+        let mut bundle = HybridKeyBundle::new();
+        bundle.encoding = parsed_seed.encoding();
+        let signature_mapping = signature::get_id_to_ref_mapping();
+        for scheme_id in parsed_seed.signature_scheme_ids.iter() {
+            let scheme_impl = match signature_mapping.get(scheme_id) {
+                Some(scheme_impl) => scheme_impl,
+                None => return Err(format!("Algorithm with id {} and config {} not found!", scheme_id.0, scheme_id.1)),
+            };
+            let seed_branch = parsed_seed.scheme_seed_branch(AlgorithmPurpose::Signature, *scheme_id)?;
+            insert_keypair(&mut bundle.signature_keypairs, bundle.encoding, *scheme_id, scheme_impl.generate_keypair(&seed_branch), "seed")?;
+        }
+        let key_encapsulation_mapping = key_encapsulation::get_id_to_ref_mapping();
+        for scheme_id in parsed_seed.key_encapsulation_scheme_ids.iter() {
+            let scheme_impl = match key_encapsulation_mapping.get(scheme_id) {
+                Some(scheme_impl) => scheme_impl,
+                None => return Err(format!("Algorithm with id {} and config {} not found!", scheme_id.0, scheme_id.1)),
+            };
+            let seed_branch = parsed_seed.scheme_seed_branch(AlgorithmPurpose::KeyEncapsulation, *scheme_id)?;
+            insert_keypair(&mut bundle.key_encapsulation_keypairs, bundle.encoding, *scheme_id, scheme_impl.generate_keypair(&seed_branch), "seed")?;
+        }
+        return Ok(bundle);
+    }
+
+    // Supplied keypairs have to pass a pairwise consistency test, so a secret key that doesn't
+    // belong to its public key is caught here rather than when a peer fails to verify
+    pub fn add_signature_keypair(&mut self, name: &str, keypair: Keypair) -> Result<(), String> {
+        let mapping = signature::get_name_to_ref_mapping();
+        let scheme_ref = match mapping.get(name) {
+            Some(scheme_ref) => scheme_ref,
+            None => return Err(format!("Algorithm {} not found", name)),
+        };
+        let scheme_id = (scheme_ref.scheme_id, scheme_ref.scheme_config_id);
+        // Reject keys that don't fit the public key encoding
+        let scheme_info = scheme_ref.scheme_impl.get_scheme_info();
+        add_keyair(&mut Vec::new(), self.encoding, &keypair.pk, scheme_id.0, scheme_id.1, &scheme_info)?;
+        if let Err(e) = check_field_size(&keypair.sk, &scheme_info.sk_size_info) {
+            return Err(format!("Secret key for {} can't be used: {}", name, e));
+        }
+        signature_pairwise_consistency_test(scheme_ref.scheme_impl.as_ref(), &keypair)?;
+        return insert_keypair(&mut self.signature_keypairs, self.encoding, scheme_id, keypair, name);
+    }
+
+    pub fn add_key_encapsulation_keypair(&mut self, name: &str, keypair: Keypair) -> Result<(), String> {
+        let mapping = key_encapsulation::get_name_to_ref_mapping();
+        let scheme_ref = match mapping.get(name) {
+            Some(scheme_ref) => scheme_ref,
+            None => return Err(format!("Algorithm {} not found", name)),
+        };
+        let scheme_id = (scheme_ref.scheme_id, scheme_ref.scheme_config_id);
+        let scheme_info = scheme_ref.scheme_impl.get_scheme_info();
+        add_keyair(&mut Vec::new(), self.encoding, &keypair.pk, scheme_id.0, scheme_id.1, &scheme_info)?;
+        if let Err(e) = check_field_size(&keypair.sk, &scheme_info.sk_size_info) {
+            return Err(format!("Secret key for {} can't be used: {}", name, e));
+        }
+        key_encapsulation_pairwise_consistency_test(scheme_ref.scheme_impl.as_ref(), &keypair)?;
+        return insert_keypair(&mut self.key_encapsulation_keypairs, self.encoding, scheme_id, keypair, name);
+    }
+
+    fn keypairs(&self, purpose: AlgorithmPurpose) -> &[((u16, u16), Keypair)] {
+        return match purpose {
+            AlgorithmPurpose::Signature => &self.signature_keypairs,
+            AlgorithmPurpose::KeyEncapsulation => &self.key_encapsulation_keypairs,
+        };
+    }
+
+    pub fn public_key<P: KeyPurpose>(&self) -> CombinedPublicKey<P> {
+        let components: Vec<((u16, u16), &[u8])> = self.keypairs(P::PURPOSE).iter().map(|(scheme_id, keypair)| (*scheme_id, keypair.pk.as_slice())).collect();
+        let public_keys = encode_combined_public_key(P::PURPOSE, self.encoding, &components).expect("Public key sizes were checked when the keypair was added");
+        return CombinedPublicKey::from_bytes(&public_keys).expect("Key bundle holds valid components");
+    }
+
+    pub fn signature_public_key(&self) -> SignaturePublicKey {
+        return self.public_key();
+    }
+
+    pub fn key_encapsulation_public_key(&self) -> KeyEncapsulationPublicKey {
+        return self.public_key();
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<HybridSignature, String> {
+        let signature = sign_message_with_keypairs(&self.signature_keypairs, self.encoding, message, None)?;
+        return HybridSignature::from_bytes(&signature);
+    }
+
+    pub fn decapsulate(&self, ciphertext: &HybridCiphertext) -> Result<SharedSecret, String> {
+        return decapsulate_with_secret_keys(ciphertext.as_bytes(), self.encoding, &|scheme_id| {
+            return match self.key_encapsulation_keypairs.iter().find(|(id, _)| id == scheme_id) {
+                Some((_, keypair)) => Ok(keypair.sk.clone().into()),
+                None => Err(format!(
+                    "Key bundle has no keypair with id {} and config {}",
+                    scheme_id.0, scheme_id.1
+                )),
+            };
+        });
+    }
+}
+
+impl HybridSigner for HybridKeyBundle {
+    fn signature_public_key(&self) -> Result<SignaturePublicKey, String> {
+        return Ok(HybridKeyBundle::signature_public_key(self));
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<HybridSignature, String> {
+        return HybridKeyBundle::sign(self, message);
+    }
+}
+
+impl HybridDecapsulator for HybridKeyBundle {
+    fn key_encapsulation_public_key(&self) -> Result<KeyEncapsulationPublicKey, String> {
+        return Ok(HybridKeyBundle::key_encapsulation_public_key(self));
+    }
+
+    fn decapsulate(&self, ciphertext: &HybridCiphertext) -> Result<SharedSecret, String> {
+        return HybridKeyBundle::decapsulate(self, ciphertext);
+    }
+}
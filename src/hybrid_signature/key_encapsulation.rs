@@ -1,96 +1,114 @@
-use crate::schemes::key_encapsulation::get_id_to_ref_mapping;
-use crate::hybrid_signature::seed_branch::create_scheme_seed_branch;
-use crate::hybrid_signature::seed_parser::parse_seed;
-use crate::schemes::{AlgorithmPurpose, SizeKind};
-use crate::parse_combined_public_key;
-use std::convert::TryInto;
-use std::mem;
-use hex_literal::hex;
-
-// echo -n "I'm Peter and I have nothing up my sleeve, this is a salt for hashing shared secrets." | openssl sha384
-const SHARED_SECRET_SALT: &[u8; 48] = &hex!("ca394d16444e060f4006af7a1a44662e29dbcaf7f46953439253fd253e1b9fd6cb0893192f674360c53555615cb36139");
-
-pub fn encapsulate(_seed: &[u8], pk_other_bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
-    let mapping = get_id_to_ref_mapping();
-    let parsed_combined_public_key =
-        parse_combined_public_key(AlgorithmPurpose::KeyEncapsulation, &pk_other_bytes).unwrap();
-    let mut ciphertexts = Vec::<u8>::new();
-
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(SHARED_SECRET_SALT);
-    
-    for (scheme_id, pk_slice) in parsed_combined_public_key.id_mapping.iter() {
-        let scheme_impl = mapping.get(scheme_id).expect(
-            format!(
-                "Algorithm not with id {} and config {} not found!",
-                scheme_id.0, scheme_id.1
-            )
-            .as_str(),
-        );
-        
-        // Create shared secret and ciphertext
-        let (ss, ct) = scheme_impl.encapsulate(&pk_slice);
-        hasher.update(&ss);
-
-        // Start by pushing the scheme id and configuration
-        ciphertexts.push(scheme_id.0);
-        ciphertexts.push(scheme_id.1);
-
-        // Add size data
-        let scheme_info = scheme_impl.get_scheme_info();
-        if matches!(scheme_info.ct_size_info.kind, SizeKind::VariableSized) {
-            // Add byte length if nessecary
-            let le_bytes = &ct.len().to_le_bytes();
-            let slice_end = scheme_info.ct_size_info.variable_size_bytelen.unwrap() as usize;
-            ciphertexts.extend(&le_bytes[..slice_end]);
-        }
-        ciphertexts.extend(&ct);
-    }
-    return (hasher.finalize().as_bytes().to_vec(), ciphertexts);
-}
-
-pub fn decapsulate(seed: &[u8], ciphertext: &[u8]) -> Vec<u8> {
-    let mapping = get_id_to_ref_mapping();
-    let mut idx: usize = 0;
-
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(SHARED_SECRET_SALT);
-    
-    while idx < ciphertext.len() {
-        // Get the ID and configuration
-        let scheme_id = (ciphertext[idx], ciphertext[idx + 1]);
-        let scheme_impl = mapping.get(&scheme_id).expect(
-            format!(
-                "Algorithm not with id {} and config {} not found!",
-                scheme_id.0, scheme_id.1
-            )
-            .as_str(),
-        );
-        idx += 2;
-        let scheme_info = scheme_impl.get_scheme_info();
-        let ciphertext_length = match scheme_info.ct_size_info.kind {
-            SizeKind::VariableSized => {
-                let ciphertext_len_byte_size =
-                scheme_info.ct_size_info.variable_size_bytelen.unwrap() as usize;
-                let slice = &ciphertext[idx..idx + ciphertext_len_byte_size];
-                idx += ciphertext_len_byte_size;
-                let mut vec_bytes = slice.to_vec();
-                vec_bytes.resize(mem::size_of::<usize>(), 0u8);
-                usize::from_le_bytes(vec_bytes[..].try_into().unwrap())
-            }
-            SizeKind::FixedSized => {
-                scheme_info.ct_size_info.fixed_size.expect("When ct_size_info.kind == SizeKind::FixedSized, ct_size_info.fixed_size must be set!") as usize
-            }
-        };
-        let ciphertext = &ciphertext[idx..idx + ciphertext_length];
-        idx += ciphertext_length;
-
-        // Generate keypair
-        let parsed_seed = parse_seed(&seed);
-        let seed_branch = create_scheme_seed_branch(&parsed_seed.seed, AlgorithmPurpose::KeyEncapsulation, scheme_id.0, scheme_id.1);
-        let keypair = scheme_impl.generate_keypair(&seed_branch);
-        let ss = scheme_impl.decapsulate(&ciphertext, &keypair.sk);
-        hasher.update(&ss);
-    }
-    return hasher.finalize().as_bytes().to_vec();
-}
+use crate::schemes::key_encapsulation::{get_id_to_ref_mapping, KeyEncapsulationScheme};
+use crate::hybrid_signature::seed_parser::parse_seed;
+use crate::hybrid_signature::sized_field::{decode_sized_field, encode_sized_field};
+use crate::hybrid_signature::{ComponentEncoding, SharedSecret};
+use crate::schemes::AlgorithmPurpose;
+use crate::secret::SecretBytes;
+use crate::parse_combined_public_key;
+use hex_literal::hex;
+use rand_core::CryptoRngCore;
+
+// echo -n "I'm Peter and I have nothing up my sleeve, this is a salt for hashing shared secrets." | openssl sha384
+const SHARED_SECRET_SALT: &[u8; 48] = &hex!("ca394d16444e060f4006af7a1a44662e29dbcaf7f46953439253fd253e1b9fd6cb0893192f674360c53555615cb36139");
+
+pub(crate) fn combine_shared_secrets(shared_secrets: &[SecretBytes]) -> SharedSecret {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(SHARED_SECRET_SALT);
+    for ss in shared_secrets.iter() {
+        hasher.update(ss);
+    }
+    return SharedSecret(hasher.finalize().as_bytes().to_vec().into());
+}
+
+// Every scheme draws its encapsulation randomness from the backend
+pub fn encapsulate(pk_other_bytes: &[u8]) -> Result<(SharedSecret, Vec<u8>), String> {
+    return encapsulate_components(pk_other_bytes, &mut |scheme_impl, pk_slice| {
+        return Ok(scheme_impl.encapsulate(pk_slice));
+    });
+}
+
+// Every scheme draws its encapsulation randomness from rng, fails if a scheme can't
+pub fn encapsulate_with_rng(pk_other_bytes: &[u8], rng: &mut dyn CryptoRngCore) -> Result<(SharedSecret, Vec<u8>), String> {
+    return encapsulate_components(pk_other_bytes, &mut |scheme_impl, pk_slice| {
+        return scheme_impl.encapsulate_with_rng(pk_slice, rng);
+    });
+}
+
+type EncapsulateComponent<'a> = dyn FnMut(&dyn KeyEncapsulationScheme, &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> + 'a;
+
+fn encapsulate_components(pk_other_bytes: &[u8], encapsulate_component: &mut EncapsulateComponent) -> Result<(SharedSecret, Vec<u8>), String> {
+    let mapping = get_id_to_ref_mapping();
+    let parsed_combined_public_key = parse_combined_public_key(AlgorithmPurpose::KeyEncapsulation, &pk_other_bytes)?;
+    let mut ciphertexts = Vec::<u8>::new();
+    let mut shared_secrets = Vec::<SecretBytes>::new();
+
+    // Ciphertexts follow the order of the components in the public key
+    for (scheme_id, pk_slice) in parsed_combined_public_key.components.iter() {
+        let scheme_impl = match mapping.get(scheme_id) {
+            Some(scheme_impl) => scheme_impl,
+            None => return Err(format!("Algorithm with id {} and config {} not found!", scheme_id.0, scheme_id.1)),
+        };
+
+        // Create shared secret and ciphertext
+        let (ss, ct) = encapsulate_component(scheme_impl.as_ref(), &pk_slice)?;
+        shared_secrets.push(ss.into());
+
+        // Start by pushing the scheme id and configuration
+        parsed_combined_public_key.encoding.encode_scheme_id(*scheme_id, &mut ciphertexts)?;
+
+        // Add size data
+        let scheme_info = scheme_impl.get_scheme_info();
+        if let Err(e) = encode_sized_field(&mut ciphertexts, parsed_combined_public_key.encoding, &ct, &scheme_info.ct_size_info) {
+            return Err(format!(
+                "Ciphertext of algorithm with id {} and config {} can't be encoded: {}",
+                scheme_id.0, scheme_id.1, e
+            ));
+        }
+    }
+    return Ok((combine_shared_secrets(&shared_secrets), ciphertexts));
+}
+
+// Fails on ciphertexts that don't parse or hold schemes the seed doesn't have
+pub fn decapsulate(seed: &[u8], ciphertext: &[u8]) -> Result<SharedSecret, String> {
+    let mapping = get_id_to_ref_mapping();
+    let parsed_seed = parse_seed(&seed)?;
+    return decapsulate_with_secret_keys(ciphertext, parsed_seed.encoding(), &|scheme_id| {
+        if !parsed_seed.key_encapsulation_scheme_ids.contains(scheme_id) {
+            return Err(format!("Seed has no key encapsulation scheme with id {} and config {}", scheme_id.0, scheme_id.1));
+        }
+        let scheme_impl = match mapping.get(scheme_id) {
+            Some(scheme_impl) => scheme_impl,
+            None => return Err(format!("Algorithm with id {} and config {} not found!", scheme_id.0, scheme_id.1)),
+        };
+        // Generate keypair
+        let seed_branch = parsed_seed.scheme_seed_branch(AlgorithmPurpose::KeyEncapsulation, *scheme_id)?;
+        let keypair = scheme_impl.generate_keypair(&seed_branch);
+        return Ok(keypair.sk.clone().into());
+    });
+}
+
+type SecretKeyFor<'a> = dyn Fn(&(u16, u16)) -> Result<SecretBytes, String> + 'a;
+
+// Decapsulates every component with the secret key secret_key_for returns for its scheme.
+// The ciphertext uses the scheme id encoding of the key it was encapsulated to.
+pub(crate) fn decapsulate_with_secret_keys(ciphertext: &[u8], encoding: ComponentEncoding, secret_key_for: &SecretKeyFor) -> Result<SharedSecret, String> {
+    let mapping = get_id_to_ref_mapping();
+    let mut idx: usize = 0;
+    let mut shared_secrets = Vec::<SecretBytes>::new();
+
+    while idx < ciphertext.len() {
+        // Get the ID and configuration
+        let scheme_id = encoding.decode_scheme_id(ciphertext, &mut idx)?;
+        let scheme_impl = match mapping.get(&scheme_id) {
+            Some(scheme_impl) => scheme_impl,
+            None => return Err(format!("Algorithm with id {} and config {} not found!", scheme_id.0, scheme_id.1)),
+        };
+        let scheme_info = scheme_impl.get_scheme_info();
+        let ciphertext = decode_sized_field(ciphertext, &mut idx, encoding, &scheme_info.ct_size_info)?;
+
+        let sk = secret_key_for(&scheme_id)?;
+        let ss = scheme_impl.decapsulate(&ciphertext, &sk);
+        shared_secrets.push(ss.into());
+    }
+    return Ok(combine_shared_secrets(&shared_secrets));
+}
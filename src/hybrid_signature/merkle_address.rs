@@ -0,0 +1,367 @@
+use crate::hybrid_signature::combined_public_key::{add_keyair, encode_combined_public_key};
+use crate::hybrid_signature::sized_field::{decode_sized_field, encode_sized_field};
+use crate::hybrid_signature::{verify_message_strict, ComponentEncoding, AddressVerificationError, HybridSignature, SignaturePublicKey};
+use crate::scheme_info_mapping::get_id_to_info_mapping;
+use crate::schemes::AlgorithmPurpose;
+use crate::varint::{decode_varint, encode_varint};
+
+const ADDRESS_LEN: usize = 32;
+const HASH_LEN: usize = 32;
+
+// Address mode for combined signature keys with large components. Instead of hashing the whole
+// key, the address commits to a Merkle tree with one leaf per component, sorted by scheme id, so
+// a signature only has to reveal the components it uses plus their paths. Leaves hash the
+// encoded component (scheme id || sized public key). A node without a sibling moves up a level
+// unchanged. The address hashes the leaf count together with the root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleAddress(Vec<u8>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisclosedComponent {
+    index: usize,
+    scheme_id: (u16, u16),
+    public_key: Vec<u8>,
+    path: Vec<[u8; HASH_LEN]>,
+}
+
+// Signature by a subset of the components together with their public keys and Merkle paths:
+// leaf count (varint) || component count (varint) ||
+// index (varint) || scheme id || sized public key || path length (varint) || path hashes || ... ||
+// signature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleSignature {
+    leaf_count: usize,
+    components: Vec<DisclosedComponent>,
+    signature: HybridSignature,
+}
+
+fn hash_with_tag(tag: u8, parts: &[&[u8]]) -> [u8; HASH_LEN] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[
+        3, // = purpose number for Merkle-committed combined public keys
+        tag,
+    ]);
+    for part in parts.iter() {
+        hasher.update(part);
+    }
+    return *hasher.finalize().as_bytes();
+}
+
+fn encode_component(scheme_id: (u16, u16), public_key: &[u8]) -> Result<Vec<u8>, String> {
+    let mapping = get_id_to_info_mapping(AlgorithmPurpose::Signature);
+    let scheme_info = match mapping.get(&scheme_id) {
+        Some(scheme_info) => scheme_info,
+        None => {
+            return Err(format!(
+                "Algorithm with id {} and config {} not found!",
+                scheme_id.0, scheme_id.1
+            ));
+        }
+    };
+    let mut component = Vec::new();
+    add_keyair(&mut component, ComponentEncoding::Varint, public_key, scheme_id.0, scheme_id.1, scheme_info)?;
+    return Ok(component);
+}
+
+fn hash_leaf(component: &[u8]) -> [u8; HASH_LEN] {
+    return hash_with_tag(0, &[component]);
+}
+
+fn hash_node(left: &[u8; HASH_LEN], right: &[u8; HASH_LEN]) -> [u8; HASH_LEN] {
+    return hash_with_tag(1, &[left, right]);
+}
+
+fn hash_root(leaf_count: usize, root: &[u8]) -> Vec<u8> {
+    let mut count = Vec::new();
+    encode_varint(leaf_count as u64, &mut count);
+    return hash_with_tag(2, &[&count, root]).to_vec();
+}
+
+// Every level of the tree, leaves first and the root last
+fn merkle_levels(leaves: Vec<[u8; HASH_LEN]>) -> Vec<Vec<[u8; HASH_LEN]>> {
+    let mut levels = vec![leaves];
+    while levels[levels.len() - 1].len() > 1 {
+        let level = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_node(left, right),
+                _ => pair[0],
+            })
+            .collect();
+        levels.push(level);
+    }
+    return levels;
+}
+
+fn merkle_path(levels: &[Vec<[u8; HASH_LEN]>], mut index: usize) -> Vec<[u8; HASH_LEN]> {
+    let mut path = Vec::new();
+    for level in levels[..levels.len() - 1].iter() {
+        if let Some(sibling) = level.get(index ^ 1) {
+            path.push(*sibling);
+        }
+        index /= 2;
+    }
+    return path;
+}
+
+// Walks a path up to the root, or returns None if the path has the wrong length for the index
+fn root_from_path(leaf: [u8; HASH_LEN], mut index: usize, leaf_count: usize, path: &[[u8; HASH_LEN]]) -> Option<[u8; HASH_LEN]> {
+    let mut hash = leaf;
+    let mut width = leaf_count;
+    let mut siblings = path.iter();
+    while width > 1 {
+        if index % 2 == 1 {
+            hash = hash_node(siblings.next()?, &hash);
+        } else if index + 1 < width {
+            hash = hash_node(&hash, siblings.next()?);
+        }
+        index /= 2;
+        width = width.div_ceil(2);
+    }
+    return match siblings.next() {
+        Some(_) => None,
+        None => Some(hash),
+    };
+}
+
+// Components sorted by scheme id, so headers and component order don't change the address
+fn sorted_components(public_key: &SignaturePublicKey) -> Vec<((u16, u16), Vec<u8>)> {
+    let mut components = public_key.parse().components;
+    components.sort_by_key(|(scheme_id, _)| *scheme_id);
+    return components;
+}
+
+fn leaves(components: &[((u16, u16), Vec<u8>)]) -> Vec<[u8; HASH_LEN]> {
+    return components
+        .iter()
+        .map(|(scheme_id, pk)| hash_leaf(&encode_component(*scheme_id, pk).expect("Components of a parsed key can be encoded")))
+        .collect();
+}
+
+impl MerkleAddress {
+    pub fn from_public_key(public_key: &SignaturePublicKey) -> Self {
+        let components = sorted_components(public_key);
+        let levels = merkle_levels(leaves(&components));
+        let root = levels[levels.len() - 1].first().map_or(&[][..], |root| &root[..]);
+        return MerkleAddress(hash_root(components.len(), root));
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != ADDRESS_LEN {
+            return Err(format!("Address must be {} bytes", ADDRESS_LEN));
+        }
+        return Ok(MerkleAddress(bytes.to_vec()));
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl AsRef<[u8]> for MerkleAddress {
+    fn as_ref(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl DisclosedComponent {
+    pub fn index(&self) -> usize {
+        return self.index;
+    }
+
+    pub fn scheme_id(&self) -> (u16, u16) {
+        return self.scheme_id;
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        return &self.public_key;
+    }
+}
+
+impl MerkleSignature {
+    // Keeps only the signature components of the given schemes and discloses the matching
+    // components of the public key
+    pub fn new(public_key: &SignaturePublicKey, signature: &HybridSignature, scheme_ids: &[(u16, u16)]) -> Result<Self, String> {
+        let components = sorted_components(public_key);
+        let levels = merkle_levels(leaves(&components));
+        let mut disclosed = Vec::new();
+        for (index, (scheme_id, pk)) in components.iter().enumerate() {
+            if scheme_ids.contains(scheme_id) {
+                disclosed.push(DisclosedComponent {
+                    index,
+                    scheme_id: *scheme_id,
+                    public_key: pk.clone(),
+                    path: merkle_path(&levels, index),
+                });
+            }
+        }
+        if disclosed.len() != scheme_ids.len() {
+            return Err("Public key lacks some of the schemes to disclose".to_string());
+        }
+
+        // The signature follows the encoding of the public key, the selected components are
+        // encoded with varint ids like the leaves
+        let encoding = public_key.parse().encoding;
+        let mapping = get_id_to_info_mapping(AlgorithmPurpose::Signature);
+        let signature = signature.as_bytes();
+        let mut selected = Vec::new();
+        let mut idx: usize = 0;
+        while idx < signature.len() {
+            let scheme_id = encoding.decode_scheme_id(signature, &mut idx)?;
+            let scheme_info = match mapping.get(&scheme_id) {
+                Some(scheme_info) => scheme_info,
+                None => {
+                    return Err(format!(
+                        "Algorithm with id {} and config {} not found!",
+                        scheme_id.0, scheme_id.1
+                    ));
+                }
+            };
+            let component = decode_sized_field(signature, &mut idx, encoding, &scheme_info.ct_size_info)?;
+            if scheme_ids.contains(&scheme_id) {
+                ComponentEncoding::Varint.encode_scheme_id(scheme_id, &mut selected)?;
+                encode_sized_field(&mut selected, ComponentEncoding::Varint, component, &scheme_info.ct_size_info)?;
+            }
+        }
+        let signature = HybridSignature::from_bytes(&selected)?;
+        if signature.scheme_ids().len() != scheme_ids.len() {
+            return Err("Signature lacks some of the schemes to disclose".to_string());
+        }
+        return Ok(MerkleSignature {
+            leaf_count: components.len(),
+            components: disclosed,
+            signature,
+        });
+    }
+
+    pub fn components(&self) -> &[DisclosedComponent] {
+        return &self.components;
+    }
+
+    pub fn signature(&self) -> &HybridSignature {
+        return &self.signature;
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_varint(self.leaf_count as u64, &mut out);
+        encode_varint(self.components.len() as u64, &mut out);
+        for component in self.components.iter() {
+            encode_varint(component.index as u64, &mut out);
+            out.extend(encode_component(component.scheme_id, &component.public_key).expect("Disclosed components were parsed"));
+            encode_varint(component.path.len() as u64, &mut out);
+            for hash in component.path.iter() {
+                out.extend(hash);
+            }
+        }
+        out.extend(self.signature.as_bytes());
+        return out;
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mapping = get_id_to_info_mapping(AlgorithmPurpose::Signature);
+        let mut idx: usize = 0;
+        let leaf_count = usize::try_from(decode_varint(bytes, &mut idx)?).map_err(|_| "Leaf count is too large".to_string())?;
+        let component_count = decode_varint(bytes, &mut idx)?;
+        let mut components = Vec::new();
+        for _ in 0..component_count {
+            let index = usize::try_from(decode_varint(bytes, &mut idx)?).map_err(|_| "Leaf index is too large".to_string())?;
+            let scheme_id = ComponentEncoding::Varint.decode_scheme_id(bytes, &mut idx)?;
+            let scheme_info = match mapping.get(&scheme_id) {
+                Some(scheme_info) => scheme_info,
+                None => {
+                    return Err(format!(
+                        "Algorithm with id {} and config {} not found!",
+                        scheme_id.0, scheme_id.1
+                    ));
+                }
+            };
+            let public_key = decode_sized_field(bytes, &mut idx, ComponentEncoding::Varint, &scheme_info.pk_size_info)?.to_vec();
+            let path_len = decode_varint(bytes, &mut idx)?;
+            if path_len > ((bytes.len() - idx) / HASH_LEN) as u64 {
+                return Err("Merkle signature is truncated".to_string());
+            }
+            let path = bytes[idx..idx + path_len as usize * HASH_LEN]
+                .chunks(HASH_LEN)
+                .map(|hash| hash.try_into().expect("Chunks are HASH_LEN bytes"))
+                .collect();
+            idx += path_len as usize * HASH_LEN;
+            components.push(DisclosedComponent {
+                index,
+                scheme_id,
+                public_key,
+                path,
+            });
+        }
+        return Ok(MerkleSignature {
+            leaf_count,
+            components,
+            signature: HybridSignature::from_bytes(&bytes[idx..])?,
+        });
+    }
+}
+
+// Checks every disclosed component against the address, that the verifier's required schemes are
+// among them and that the signature covers exactly the disclosed components. Without required
+// schemes a signer could disclose just its weakest component.
+pub fn verify_with_merkle_address(message: &[u8], bundle: &MerkleSignature, address: &MerkleAddress, required_scheme_ids: &[(u16, u16)]) -> Result<(), AddressVerificationError> {
+    if bundle.components.is_empty() {
+        return Err(AddressVerificationError::MissingComponents);
+    }
+    let mut components = Vec::new();
+    let mut previous_index = None;
+    for component in bundle.components.iter() {
+        // Distinct leaves in tree order, which is also the canonical component order
+        if component.index >= bundle.leaf_count || previous_index.is_some_and(|previous| previous >= component.index) {
+            return Err(AddressVerificationError::AddressMismatch);
+        }
+        previous_index = Some(component.index);
+        let encoded = match encode_component(component.scheme_id, &component.public_key) {
+            Ok(encoded) => encoded,
+            Err(_) => return Err(AddressVerificationError::AddressMismatch),
+        };
+        let root = match root_from_path(hash_leaf(&encoded), component.index, bundle.leaf_count, &component.path) {
+            Some(root) => root,
+            None => return Err(AddressVerificationError::AddressMismatch),
+        };
+        if hash_root(bundle.leaf_count, &root) != address.0 {
+            return Err(AddressVerificationError::AddressMismatch);
+        }
+        components.push((component.scheme_id, component.public_key.as_slice()));
+    }
+    let combined_public_key = match encode_combined_public_key(AlgorithmPurpose::Signature, ComponentEncoding::Varint, &components) {
+        Ok(combined_public_key) => combined_public_key,
+        Err(_) => return Err(AddressVerificationError::AddressMismatch),
+    };
+    let disclosed = |scheme_id: &(u16, u16)| bundle.components.iter().any(|component| component.scheme_id == *scheme_id);
+    if !required_scheme_ids.iter().all(disclosed) {
+        return Err(AddressVerificationError::MissingComponents);
+    }
+    if !verify_message_strict(message, &combined_public_key, bundle.signature.as_bytes()) {
+        return Err(AddressVerificationError::InvalidSignature);
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merkle_paths_lead_to_root() {
+        for leaf_count in 1..10usize {
+            let leaves: Vec<[u8; HASH_LEN]> = (0..leaf_count).map(|i| hash_leaf(&[i as u8])).collect();
+            let levels = merkle_levels(leaves.clone());
+            let root = levels[levels.len() - 1][0];
+            for (index, leaf) in leaves.iter().enumerate() {
+                let path = merkle_path(&levels, index);
+                assert_eq!(root_from_path(*leaf, index, leaf_count, &path), Some(root));
+                // Paths only fit their own position
+                if leaf_count > 1 {
+                    assert_ne!(root_from_path(*leaf, (index + 1) % leaf_count, leaf_count, &path), Some(root));
+                }
+                assert_eq!(root_from_path(*leaf, index, leaf_count, &[path.clone(), vec![root]].concat()), None);
+            }
+        }
+    }
+}
@@ -1,18 +1,35 @@
-mod address;
-mod combined_public_key;
-mod seed_parser;
-mod seed_branch;
-mod signature;
-mod key_encapsulation;
-mod combined_public_key_parser;
-mod seed_generator;
-
-pub use address::*;
-pub use combined_public_key::generate_combined_public_key;
-pub use seed_parser::*;
-pub use seed_branch::create_scheme_seed_branch;
-pub use signature::*;
-pub use key_encapsulation::*;
-pub use combined_public_key_parser::*;
-pub use seed_generator::generate_random_seed;
-pub use seed_generator::wrap_seed;
\ No newline at end of file
+mod address;
+mod combined_public_key;
+mod seed_parser;
+mod seed_branch;
+mod signature;
+mod key_encapsulation;
+mod combined_public_key_parser;
+mod combined_public_key_header;
+mod seed_generator;
+mod sized_field;
+mod types;
+mod key_bundle;
+mod certificate;
+mod signature_bundle;
+mod multisig;
+mod merkle_address;
+mod component_encoding;
+
+pub use address::*;
+pub use combined_public_key::{generate_combined_public_key, KeyGenerationOptions};
+pub use seed_parser::*;
+pub use seed_branch::{create_legacy_scheme_seed_branch, create_scheme_seed_branch};
+pub use signature::*;
+pub use key_encapsulation::*;
+pub use combined_public_key_parser::*;
+pub use combined_public_key_header::*;
+pub use seed_generator::{generate_random_seed, generate_random_seed_with_entropy_len, generate_random_seed_with_rng};
+pub use seed_generator::{migrate_seed, wrap_seed};
+pub use types::*;
+pub use key_bundle::HybridKeyBundle;
+pub use certificate::*;
+pub use signature_bundle::*;
+pub use multisig::*;
+pub use merkle_address::*;
+pub use component_encoding::ComponentEncoding;
\ No newline at end of file
@@ -0,0 +1,202 @@
+use crate::hybrid_signature::{hash_multisig_public_keys, AddressVerificationError, HybridSignature, HybridSigner, SignaturePublicKey};
+use crate::varint::{decode_varint, encode_varint};
+
+const ADDRESS_LEN: usize = 32;
+
+// m-of-n set of signature public keys, e.g. for treasury accounts. Its address commits to the
+// threshold and to every key, see hash_multisig_public_keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultisigPublicKey {
+    threshold: usize,
+    public_keys: Vec<SignaturePublicKey>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultisigAddress(Vec<u8>);
+
+// Reveals all n public keys and carries at least threshold signatures, each tagged with the
+// index of its signer in the key list:
+// threshold (varint) || n (varint) || n length prefixed public keys ||
+// signature count (varint) || signer index (varint) || length prefixed signature || ...
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultisigSignature {
+    public_key: MultisigPublicKey,
+    signatures: Vec<(usize, HybridSignature)>,
+}
+
+fn push_length_prefixed(out: &mut Vec<u8>, bytes: &[u8]) {
+    encode_varint(bytes.len() as u64, out);
+    out.extend(bytes);
+}
+
+fn read_length_prefixed<'a>(bytes: &'a [u8], idx: &mut usize) -> Result<&'a [u8], String> {
+    let len = decode_varint(bytes, idx)?;
+    if len > (bytes.len() - *idx) as u64 {
+        return Err("Multisig signature is truncated".to_string());
+    }
+    let field = &bytes[*idx..*idx + len as usize];
+    *idx += len as usize;
+    return Ok(field);
+}
+
+fn read_count(bytes: &[u8], idx: &mut usize) -> Result<usize, String> {
+    let count = decode_varint(bytes, idx)?;
+    // Every counted item takes at least one byte
+    if count > bytes.len() as u64 {
+        return Err("Multisig signature is truncated".to_string());
+    }
+    return Ok(count as usize);
+}
+
+impl MultisigPublicKey {
+    pub fn new(threshold: usize, public_keys: Vec<SignaturePublicKey>) -> Result<Self, String> {
+        if threshold == 0 || threshold > public_keys.len() {
+            return Err(format!("Threshold must be between 1 and {}", public_keys.len()));
+        }
+        // An empty key verifies any message, so it can't count as a signer
+        if public_keys.iter().any(|pk| pk.parse().components.is_empty()) {
+            return Err("Multisig public keys need at least one component".to_string());
+        }
+        // The same key may not count twice, even with a different header, which can change the
+        // address, or with its components listed in another order
+        let mut component_sets: Vec<_> = public_keys.iter().map(|pk| {
+            let mut components = pk.parse().components;
+            components.sort();
+            return components;
+        }).collect();
+        component_sets.sort();
+        component_sets.dedup();
+        if component_sets.len() != public_keys.len() {
+            return Err("Multisig public keys must be distinct".to_string());
+        }
+        return Ok(MultisigPublicKey { threshold, public_keys });
+    }
+
+    pub fn threshold(&self) -> usize {
+        return self.threshold;
+    }
+
+    pub fn public_keys(&self) -> &[SignaturePublicKey] {
+        return &self.public_keys;
+    }
+
+    pub fn address(&self) -> MultisigAddress {
+        let public_keys: Vec<&[u8]> = self.public_keys.iter().map(|pk| pk.as_bytes()).collect();
+        return MultisigAddress(hash_multisig_public_keys(self.threshold, &public_keys).expect("Multisig public keys were already parsed"));
+    }
+}
+
+impl MultisigAddress {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != ADDRESS_LEN {
+            return Err(format!("Address must be {} bytes", ADDRESS_LEN));
+        }
+        return Ok(MultisigAddress(bytes.to_vec()));
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl AsRef<[u8]> for MultisigAddress {
+    fn as_ref(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl MultisigSignature {
+    pub fn new(public_key: MultisigPublicKey) -> Self {
+        return MultisigSignature {
+            public_key,
+            signatures: Vec::new(),
+        };
+    }
+
+    // Signers can sign independently, each of them adds their signature in turn
+    pub fn sign<S: HybridSigner>(&mut self, signer: &S, message: &[u8]) -> Result<(), String> {
+        let signer_address = signer.signature_public_key()?.address();
+        let index = match self.public_key.public_keys.iter().position(|pk| pk.address() == signer_address) {
+            Some(index) => index,
+            None => return Err("Signer is not part of the multisig public key".to_string()),
+        };
+        return self.add_signature(index, signer.sign(message)?);
+    }
+
+    pub fn add_signature(&mut self, index: usize, signature: HybridSignature) -> Result<(), String> {
+        if index >= self.public_key.public_keys.len() {
+            return Err(format!("Signer index {} is out of range", index));
+        }
+        return match self.signatures.binary_search_by_key(&index, |(index, _)| *index) {
+            Ok(_) => Err(format!("Signer {} already signed", index)),
+            Err(idx) => {
+                self.signatures.insert(idx, (index, signature));
+                Ok(())
+            }
+        };
+    }
+
+    pub fn public_key(&self) -> &MultisigPublicKey {
+        return &self.public_key;
+    }
+
+    pub fn signatures(&self) -> &[(usize, HybridSignature)] {
+        return &self.signatures;
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_varint(self.public_key.threshold as u64, &mut out);
+        encode_varint(self.public_key.public_keys.len() as u64, &mut out);
+        for public_key in self.public_key.public_keys.iter() {
+            push_length_prefixed(&mut out, public_key.as_bytes());
+        }
+        encode_varint(self.signatures.len() as u64, &mut out);
+        for (index, signature) in self.signatures.iter() {
+            encode_varint(*index as u64, &mut out);
+            push_length_prefixed(&mut out, signature.as_bytes());
+        }
+        return out;
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut idx: usize = 0;
+        let threshold = decode_varint(bytes, &mut idx)?;
+        let key_count = read_count(bytes, &mut idx)?;
+        let mut public_keys = Vec::with_capacity(key_count);
+        for _ in 0..key_count {
+            public_keys.push(SignaturePublicKey::from_bytes(read_length_prefixed(bytes, &mut idx)?)?);
+        }
+        let threshold = usize::try_from(threshold).map_err(|_| "Threshold is too large".to_string())?;
+        let mut multisig_signature = MultisigSignature::new(MultisigPublicKey::new(threshold, public_keys)?);
+        let signature_count = read_count(bytes, &mut idx)?;
+        for _ in 0..signature_count {
+            let index = decode_varint(bytes, &mut idx)?;
+            let index = usize::try_from(index).map_err(|_| format!("Signer index {} is out of range", index))?;
+            let signature = HybridSignature::from_bytes(read_length_prefixed(bytes, &mut idx)?)?;
+            multisig_signature.add_signature(index, signature)?;
+        }
+        if idx != bytes.len() {
+            return Err("Multisig signature has trailing bytes".to_string());
+        }
+        return Ok(multisig_signature);
+    }
+}
+
+// Checks that the revealed keys and threshold match the address, that every included signature
+// verifies strictly under its signer's key and that at least threshold distinct signers signed
+pub fn verify_multisig(message: &[u8], bundle: &MultisigSignature, address: &MultisigAddress) -> Result<(), AddressVerificationError> {
+    if bundle.public_key.address() != *address {
+        return Err(AddressVerificationError::AddressMismatch);
+    }
+    for (index, signature) in bundle.signatures.iter() {
+        if !bundle.public_key.public_keys[*index].verify(message, signature) {
+            return Err(AddressVerificationError::InvalidSignature);
+        }
+    }
+    // Signer indices are kept distinct, so every signature is from another signer
+    if bundle.signatures.len() < bundle.public_key.threshold {
+        return Err(AddressVerificationError::ThresholdNotMet);
+    }
+    return Ok(());
+}
@@ -1,16 +1,35 @@
-use crate::schemes::AlgorithmPurpose;
-
-pub fn create_scheme_seed_branch(seed: &[u8], purpose: AlgorithmPurpose, scheme_id: u8, scheme_config_id: u8) -> Vec<u8> {
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(&[
-        match purpose {
-            AlgorithmPurpose::Signature => 0,
-            AlgorithmPurpose::KeyEncapsulation => 1
-        }, // = purpose number for branching to different algorithms
-        scheme_id,
-        scheme_config_id,
-    ]);
-    hasher.update(&seed);
-    let result = hasher.finalize();
-    return result.as_bytes().to_vec();
+use crate::schemes::AlgorithmPurpose;
+use crate::secret::SecretBytes;
+use crate::varint::encode_scheme_id;
+
+pub fn create_scheme_seed_branch(seed: &[u8], purpose: AlgorithmPurpose, scheme_id: u16, scheme_config_id: u16) -> SecretBytes {
+    let mut hasher = blake3::Hasher::new();
+    let mut prefix = vec![
+        match purpose {
+            AlgorithmPurpose::Signature => 0,
+            AlgorithmPurpose::KeyEncapsulation => 1
+        }, // = purpose number for branching to different algorithms
+    ];
+    encode_scheme_id((scheme_id, scheme_config_id), &mut prefix);
+    hasher.update(&prefix);
+    hasher.update(&seed);
+    let result = hasher.finalize();
+    return result.as_bytes().to_vec().into();
+}
+
+// Seed branches of seeds up to version 2, which hash the ids as single bytes. Ids below 128
+// branch the same way in both, larger ones don't.
+pub fn create_legacy_scheme_seed_branch(seed: &[u8], purpose: AlgorithmPurpose, scheme_id: u8, scheme_config_id: u8) -> SecretBytes {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[
+        match purpose {
+            AlgorithmPurpose::Signature => 0,
+            AlgorithmPurpose::KeyEncapsulation => 1
+        }, // = purpose number for branching to different algorithms
+        scheme_id,
+        scheme_config_id,
+    ]);
+    hasher.update(&seed);
+    let result = hasher.finalize();
+    return result.as_bytes().to_vec().into();
 }
\ No newline at end of file
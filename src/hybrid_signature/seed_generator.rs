@@ -1,40 +1,104 @@
-use crate::schemes::key_encapsulation;
-use crate::schemes::signature;
-use std::convert::TryInto;
-
-pub fn generate_random_seed(
-    signature_algorithms: &[String],
-    key_encapsulation_algorithms: &[String],
-) -> Vec<u8> {
-    let mut seed = [0u8; 48];
-    getrandom::getrandom(&mut seed).expect("Random seed could not be loaded!");
-    return wrap_seed(signature_algorithms, key_encapsulation_algorithms, seed);
-}
-
-pub fn wrap_seed(
-    signature_algorithms: &[String],
-    key_encapsulation_algorithms: &[String],
-    seed: [u8; 48]
-) -> Vec<u8> {
-    let signature_mapping = signature::get_name_to_ref_mapping();
-    let mut result = Vec::<u8>::new();
-    result.push(signature_algorithms.len().try_into().unwrap());
-    for algorithm in signature_algorithms {
-        let info = signature_mapping
-            .get(algorithm)
-            .expect(format!("Algorithm {} not found", algorithm).as_str());
-        result.push(info.scheme_id);
-        result.push(info.scheme_config_id);
-    }
-    let key_encapsulation_mapping = key_encapsulation::get_name_to_ref_mapping();
-    for algorithm in key_encapsulation_algorithms {
-        let info = key_encapsulation_mapping
-            .get(algorithm)
-            .expect(format!("Algorithm {} not found", algorithm).as_str());
-        result.push(info.scheme_id);
-        result.push(info.scheme_config_id);
-    }
-    result.extend(seed.iter());
-    return result;
-}
-
+use crate::schemes::key_encapsulation;
+use crate::schemes::signature;
+use crate::hybrid_signature::seed_parser::{check_entropy_len, check_unique_scheme_ids, parse_seed, seed_checksum, SEED_ENTROPY_LEN, SEED_MAGIC, SEED_VERSION};
+use crate::hybrid_signature::ComponentEncoding;
+use crate::varint::{encode_scheme_id, encode_varint};
+use rand_core::{CryptoRngCore, OsRng};
+use zeroize::Zeroizing;
+
+pub fn generate_random_seed(
+    signature_algorithms: &[String],
+    key_encapsulation_algorithms: &[String],
+) -> Result<Vec<u8>, String> {
+    return generate_random_seed_with_rng(signature_algorithms, key_encapsulation_algorithms, &mut OsRng);
+}
+
+pub fn generate_random_seed_with_rng(
+    signature_algorithms: &[String],
+    key_encapsulation_algorithms: &[String],
+    rng: &mut dyn CryptoRngCore,
+) -> Result<Vec<u8>, String> {
+    return generate_random_seed_with_entropy_len(signature_algorithms, key_encapsulation_algorithms, SEED_ENTROPY_LEN, rng);
+}
+
+pub fn generate_random_seed_with_entropy_len(
+    signature_algorithms: &[String],
+    key_encapsulation_algorithms: &[String],
+    entropy_len: usize,
+    rng: &mut dyn CryptoRngCore,
+) -> Result<Vec<u8>, String> {
+    check_entropy_len(entropy_len)?;
+    let mut seed = Zeroizing::new(vec![0u8; entropy_len]);
+    if let Err(e) = rng.try_fill_bytes(&mut seed) {
+        return Err(format!("Random seed could not be loaded: {}", e));
+    }
+    return wrap_seed(signature_algorithms, key_encapsulation_algorithms, seed.as_slice());
+}
+
+pub fn wrap_seed(
+    signature_algorithms: &[String],
+    key_encapsulation_algorithms: &[String],
+    seed: impl AsRef<[u8]>
+) -> Result<Vec<u8>, String> {
+    let seed = seed.as_ref();
+    check_entropy_len(seed.len())?;
+    let signature_mapping = signature::get_name_to_ref_mapping();
+    let mut signature_scheme_ids = Vec::<(u16, u16)>::new();
+    for algorithm in signature_algorithms {
+        let info = match signature_mapping.get(algorithm) {
+            Some(info) => info,
+            None => return Err(format!("Algorithm {} not found", algorithm)),
+        };
+        signature_scheme_ids.push((info.scheme_id, info.scheme_config_id));
+    }
+    let key_encapsulation_mapping = key_encapsulation::get_name_to_ref_mapping();
+    let mut key_encapsulation_scheme_ids = Vec::<(u16, u16)>::new();
+    for algorithm in key_encapsulation_algorithms {
+        let info = match key_encapsulation_mapping.get(algorithm) {
+            Some(info) => info,
+            None => return Err(format!("Algorithm {} not found", algorithm)),
+        };
+        key_encapsulation_scheme_ids.push((info.scheme_id, info.scheme_config_id));
+    }
+    return encode_seed(&signature_scheme_ids, &key_encapsulation_scheme_ids, seed);
+}
+
+pub(crate) fn encode_seed(signature_scheme_ids: &[(u16, u16)], key_encapsulation_scheme_ids: &[(u16, u16)], entropy: &[u8]) -> Result<Vec<u8>, String> {
+    check_entropy_len(entropy.len())?;
+    check_unique_scheme_ids(signature_scheme_ids)?;
+    check_unique_scheme_ids(key_encapsulation_scheme_ids)?;
+    let mut result = Vec::<u8>::new();
+    result.extend(SEED_MAGIC);
+    result.push(SEED_VERSION);
+    for scheme_ids in [signature_scheme_ids, key_encapsulation_scheme_ids] {
+        encode_varint(scheme_ids.len() as u64, &mut result);
+        for scheme_id in scheme_ids.iter() {
+            encode_scheme_id(*scheme_id, &mut result);
+        }
+    }
+    // Entropy lengths were checked to fit in a byte
+    result.push(entropy.len() as u8);
+    result.extend(entropy);
+    let checksum = seed_checksum(&result);
+    result.extend(checksum);
+    return Ok(result);
+}
+
+// Re-encodes a seed (legacy or current) in the current format, deriving the same component keys.
+// The combined public keys move to the version 2 header, so their addresses change. Seed branches
+// of ids from 128 on differ between the formats, such seeds can't be migrated.
+pub fn migrate_seed(seed: &[u8]) -> Result<Vec<u8>, String> {
+    let parsed_seed = parse_seed(seed)?;
+    if parsed_seed.encoding() == ComponentEncoding::Legacy {
+        let scheme_ids = parsed_seed.signature_scheme_ids.iter().chain(parsed_seed.key_encapsulation_scheme_ids.iter());
+        for scheme_id in scheme_ids {
+            if scheme_id.0 >= 0x80 || scheme_id.1 >= 0x80 {
+                return Err(format!(
+                    "Algorithm with id {} and config {} would derive other keys after migration",
+                    scheme_id.0, scheme_id.1
+                ));
+            }
+        }
+    }
+    return encode_seed(&parsed_seed.signature_scheme_ids, &parsed_seed.key_encapsulation_scheme_ids, &parsed_seed.seed);
+}
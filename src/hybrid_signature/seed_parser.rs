@@ -1,32 +1,194 @@
-pub struct ParsedSeed {
-    pub seed: Vec<u8>,
-    pub signature_scheme_ids: Vec<(u8, u8)>,
-    pub key_encapsulation_scheme_ids: Vec<(u8, u8)>,
-}
-
-pub fn parse_seed(seed: &[u8]) -> ParsedSeed {
-    let seed_len = 48; // SHA384 byte len
-    let real_seed = &seed[seed.len() - seed_len..];
-    let signature_scheme_bytes_len: u8 = seed[0];
-    let key_encapsulation_scheme_bytes_len: u8 = (((seed.len() - seed_len) as u8) - (signature_scheme_bytes_len * 2) - 1) / 2;
-    let mut signature_scheme_ids: Vec<(u8, u8)> = Vec::new();
-    let mut key_encapsulation_scheme_ids: Vec<(u8, u8)> = Vec::new();
-    for n in 0..signature_scheme_bytes_len {
-        let offset: usize = (n * 2).into();
-        let scheme_id = (seed[offset + 1], seed[offset + 2]);
-        signature_scheme_ids.push(scheme_id);
-    }
-    for n in 0..key_encapsulation_scheme_bytes_len {
-        let offset: usize = (n * 2).into();
-        let scheme_id = (
-            seed[offset + (((signature_scheme_bytes_len * 2) + 1) as usize)],
-            seed[offset + (((signature_scheme_bytes_len * 2) + 2) as usize)],
-        );
-        key_encapsulation_scheme_ids.push(scheme_id);
-    }
-    return ParsedSeed {
-        seed: real_seed.to_vec(),
-        signature_scheme_ids,
-        key_encapsulation_scheme_ids,
-    };
-}
+use crate::hybrid_signature::seed_branch::{create_legacy_scheme_seed_branch, create_scheme_seed_branch};
+use crate::hybrid_signature::ComponentEncoding;
+use crate::schemes::AlgorithmPurpose;
+use crate::secret::SecretBytes;
+use crate::varint::{decode_scheme_id, decode_varint};
+
+// Seed layout since format version 3:
+// magic || version || signature count || signature ids || key encapsulation count || key encapsulation ids || entropy length || entropy || checksum
+// Counts and ids are varints. Version 2 seeds store them as single bytes, version 1 seeds
+// additionally have no entropy length and always carry 48 bytes of entropy.
+// Legacy seeds (signature count || signature ids || key encapsulation ids || entropy) are still accepted.
+pub const SEED_MAGIC: [u8; 3] = *b"HYS";
+pub const SEED_VERSION: u8 = 3;
+pub const SEED_VERSION_SINGLE_BYTE_IDS: u8 = 2;
+pub const SEED_VERSION_FIXED_ENTROPY: u8 = 1;
+pub const LEGACY_SEED_VERSION: u8 = 0;
+pub const SEED_ENTROPY_LEN: usize = 48; // SHA384 byte len
+pub const MIN_SEED_ENTROPY_LEN: usize = 32;
+pub const MAX_SEED_ENTROPY_LEN: usize = 64;
+pub const SEED_CHECKSUM_LEN: usize = 4;
+
+pub struct ParsedSeed {
+    pub version: u8,
+    pub seed: SecretBytes,
+    pub signature_scheme_ids: Vec<(u16, u16)>,
+    pub key_encapsulation_scheme_ids: Vec<(u16, u16)>,
+}
+
+impl ParsedSeed {
+    // Layout of the keys, signatures and ciphertexts the seed makes
+    pub fn encoding(&self) -> ComponentEncoding {
+        if self.version >= SEED_VERSION {
+            return ComponentEncoding::Varint;
+        }
+        return ComponentEncoding::Legacy;
+    }
+
+    // Branch of the seed that derives the keypair of one of its schemes
+    pub fn scheme_seed_branch(&self, purpose: AlgorithmPurpose, scheme_id: (u16, u16)) -> Result<SecretBytes, String> {
+        return match self.encoding() {
+            ComponentEncoding::Legacy => match (u8::try_from(scheme_id.0), u8::try_from(scheme_id.1)) {
+                (Ok(id), Ok(config_id)) => Ok(create_legacy_scheme_seed_branch(&self.seed, purpose, id, config_id)),
+                _ => Err(format!(
+                    "Algorithm with id {} and config {} doesn't fit a version {} seed",
+                    scheme_id.0, scheme_id.1, self.version
+                )),
+            },
+            ComponentEncoding::Varint => Ok(create_scheme_seed_branch(&self.seed, purpose, scheme_id.0, scheme_id.1)),
+        };
+    }
+}
+
+pub(crate) fn seed_checksum(seed_without_checksum: &[u8]) -> [u8; SEED_CHECKSUM_LEN] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[
+        2 // = purpose number for seed checksums
+    ]);
+    hasher.update(seed_without_checksum);
+    let mut checksum = [0u8; SEED_CHECKSUM_LEN];
+    checksum.copy_from_slice(&hasher.finalize().as_bytes()[..SEED_CHECKSUM_LEN]);
+    return checksum;
+}
+
+pub(crate) fn check_entropy_len(entropy_len: usize) -> Result<(), String> {
+    if entropy_len < MIN_SEED_ENTROPY_LEN || entropy_len > MAX_SEED_ENTROPY_LEN {
+        return Err(format!(
+            "Seed entropy must be between {} and {} bytes, got {}",
+            MIN_SEED_ENTROPY_LEN, MAX_SEED_ENTROPY_LEN, entropy_len
+        ));
+    }
+    return Ok(());
+}
+
+// Every scheme gets one component, a repeated id would make keys with duplicate components
+pub(crate) fn check_unique_scheme_ids(scheme_ids: &[(u16, u16)]) -> Result<(), String> {
+    for (idx, scheme_id) in scheme_ids.iter().enumerate() {
+        if scheme_ids[..idx].contains(scheme_id) {
+            return Err(format!(
+                "Seed lists algorithm with id {} and config {} more than once",
+                scheme_id.0, scheme_id.1
+            ));
+        }
+    }
+    return Ok(());
+}
+
+fn parse_varint_scheme_ids(bytes: &[u8], idx: &mut usize) -> Result<Vec<(u16, u16)>, String> {
+    let count = decode_varint(bytes, idx)?;
+    let mut scheme_ids = Vec::<(u16, u16)>::new();
+    for _ in 0..count {
+        scheme_ids.push(decode_scheme_id(bytes, idx)?);
+    }
+    return Ok(scheme_ids);
+}
+
+fn parse_scheme_ids(bytes: &[u8], idx: &mut usize) -> Result<Vec<(u16, u16)>, String> {
+    let count = match bytes.get(*idx) {
+        Some(count) => *count as usize,
+        None => return Err("Seed is truncated".to_string()),
+    };
+    *idx += 1;
+    let ids = match bytes.get(*idx..*idx + count * 2) {
+        Some(ids) => ids,
+        None => return Err("Seed is truncated".to_string()),
+    };
+    *idx += count * 2;
+    return Ok(ids.chunks(2).map(|id| (id[0] as u16, id[1] as u16)).collect());
+}
+
+fn parse_versioned_seed(seed: &[u8]) -> Result<ParsedSeed, String> {
+    if seed.len() < SEED_MAGIC.len() + 1 + SEED_CHECKSUM_LEN {
+        return Err("Seed is truncated".to_string());
+    }
+    let version = seed[SEED_MAGIC.len()];
+    if version != SEED_VERSION && version != SEED_VERSION_SINGLE_BYTE_IDS && version != SEED_VERSION_FIXED_ENTROPY {
+        return Err(format!("Unsupported seed version {}", version));
+    }
+    let (body, checksum) = seed.split_at(seed.len() - SEED_CHECKSUM_LEN);
+    if seed_checksum(body) != checksum {
+        return Err("Seed checksum mismatch".to_string());
+    }
+    let mut idx = SEED_MAGIC.len() + 1;
+    let (signature_scheme_ids, key_encapsulation_scheme_ids) = if version == SEED_VERSION {
+        (parse_varint_scheme_ids(body, &mut idx)?, parse_varint_scheme_ids(body, &mut idx)?)
+    } else {
+        (parse_scheme_ids(body, &mut idx)?, parse_scheme_ids(body, &mut idx)?)
+    };
+    let entropy_len = if version == SEED_VERSION_FIXED_ENTROPY {
+        SEED_ENTROPY_LEN
+    } else {
+        let entropy_len = match body.get(idx) {
+            Some(entropy_len) => *entropy_len as usize,
+            None => return Err("Seed is truncated".to_string()),
+        };
+        idx += 1;
+        check_entropy_len(entropy_len)?;
+        entropy_len
+    };
+    if body.len() - idx != entropy_len {
+        return Err(format!("Seed entropy must be {} bytes", entropy_len));
+    }
+    return Ok(ParsedSeed {
+        version,
+        seed: body[idx..].to_vec().into(),
+        signature_scheme_ids,
+        key_encapsulation_scheme_ids,
+    });
+}
+
+fn parse_legacy_seed(seed: &[u8]) -> Result<ParsedSeed, String> {
+    if seed.len() < SEED_ENTROPY_LEN + 1 {
+        return Err("Seed is truncated".to_string());
+    }
+    let (ids, real_seed) = seed.split_at(seed.len() - SEED_ENTROPY_LEN);
+    let mut idx = 0;
+    let signature_scheme_ids = parse_scheme_ids(ids, &mut idx)?;
+    // The key encapsulation id count is implied by whatever is left
+    let remaining = &ids[idx..];
+    if remaining.len() % 2 != 0 {
+        return Err("Seed has a dangling scheme id byte".to_string());
+    }
+    let key_encapsulation_scheme_ids = remaining.chunks(2).map(|id| (id[0] as u16, id[1] as u16)).collect();
+    return Ok(ParsedSeed {
+        version: LEGACY_SEED_VERSION,
+        seed: real_seed.to_vec().into(),
+        signature_scheme_ids,
+        key_encapsulation_scheme_ids,
+    });
+}
+
+// Legacy seeds can only start with two of the magic bytes if they hold 72 signature schemes, the
+// scheme id (89, 83) or no schemes at all, so such input is a versioned seed with a damaged
+// magic. Parsing it as a legacy seed would turn a corrupted seed into different keys.
+fn has_damaged_magic(seed: &[u8]) -> bool {
+    let matching = seed.iter().zip(SEED_MAGIC.iter()).filter(|(byte, magic_byte)| byte == magic_byte).count();
+    return matching >= SEED_MAGIC.len() - 1;
+}
+
+pub fn parse_seed(seed: &[u8]) -> Result<ParsedSeed, String> {
+    let parsed_seed = parse_seed_layout(seed)?;
+    check_unique_scheme_ids(&parsed_seed.signature_scheme_ids)?;
+    check_unique_scheme_ids(&parsed_seed.key_encapsulation_scheme_ids)?;
+    return Ok(parsed_seed);
+}
+
+fn parse_seed_layout(seed: &[u8]) -> Result<ParsedSeed, String> {
+    if seed.starts_with(&SEED_MAGIC) {
+        return parse_versioned_seed(seed);
+    }
+    if has_damaged_magic(seed) {
+        return Err("Seed magic is damaged".to_string());
+    }
+    return parse_legacy_seed(seed);
+}
@@ -1,104 +1,167 @@
-use crate::hybrid_signature::parse_combined_public_key;
-use crate::hybrid_signature::seed_branch::create_scheme_seed_branch;
-use crate::hybrid_signature::seed_parser::parse_seed;
-use crate::schemes::signature::get_id_to_ref_mapping;
-use crate::schemes::{AlgorithmPurpose, SizeKind};
-use std::convert::TryInto;
-use std::mem;
-use log::error;
-
-pub fn sign_message(seed: &[u8], message: &[u8]) -> Vec<u8> {
-    let mapping = get_id_to_ref_mapping();
-    let parsed_seed = parse_seed(&seed);
-    let mut message_signatures = Vec::<u8>::new();
-    for scheme_id in parsed_seed.signature_scheme_ids.iter() {
-        let scheme_impl = mapping.get(scheme_id).expect(
-            format!(
-                "Algorithm not with id {} and config {} not found!",
-                scheme_id.0, scheme_id.1
-            )
-            .as_str(),
-        );
-        let seed_branch = create_scheme_seed_branch(&parsed_seed.seed, AlgorithmPurpose::Signature, scheme_id.0, scheme_id.1);
-
-        // Start by pushing the scheme id and configuration
-        message_signatures.push(scheme_id.0);
-        message_signatures.push(scheme_id.1);
-        // Generate keypair
-        let keypair = scheme_impl.generate_keypair(&seed_branch);
-
-        // Sign said message
-        let signature = scheme_impl.sign_message(&keypair.sk, message);
-        // Add size data
-        let scheme_info = scheme_impl.get_scheme_info();
-        if matches!(scheme_info.ct_size_info.kind, SizeKind::VariableSized) {
-            // Add byte length if nessecary
-            let le_bytes = &signature.len().to_le_bytes();
-            let slice_end = scheme_info.ct_size_info.variable_size_bytelen.unwrap() as usize;
-            message_signatures.extend(&le_bytes[..slice_end]);
-        }
-        message_signatures.extend(&signature);
-    }
-    return message_signatures;
-}
-
-pub fn verify_message(message: &[u8], combined_public_key: &[u8], signature: &[u8]) -> bool {
-    let mapping = get_id_to_ref_mapping();
-    let mut idx: usize = 0;
-    let parsed_combined_public_key = match parse_combined_public_key(AlgorithmPurpose::Signature, &combined_public_key) {
-        Ok(pk) => {
-            pk
-        }
-        Err(e) => {
-            error!("Error parsing public key: {}... verify_message will return false!", e);
-            return false;
-        }
-    };
-    while idx < signature.len() {
-        // Get the ID and configuration
-        let scheme_id = (signature[idx], signature[idx + 1]);
-        let scheme_impl = mapping.get(&scheme_id).expect(
-            format!(
-                "Algorithm with id {} and config {} not found!",
-                scheme_id.0, scheme_id.1
-            )
-            .as_str(),
-        );
-        idx += 2;
-        let scheme_info = scheme_impl.get_scheme_info();
-        let signature_length = match scheme_info.ct_size_info.kind {
-            SizeKind::VariableSized => {
-                let signature_len_byte_size =
-                    scheme_info.ct_size_info.variable_size_bytelen.unwrap() as usize;
-                let slice = &signature[idx..idx + signature_len_byte_size];
-                idx += signature_len_byte_size;
-                let mut vec_bytes = slice.to_vec();
-                vec_bytes.resize(mem::size_of::<usize>(), 0u8);
-                usize::from_le_bytes(vec_bytes[..].try_into().unwrap())
-            }
-            SizeKind::FixedSized => {
-                scheme_info.ct_size_info.fixed_size.expect("When ct_size_info.kind == SizeKind::FixedSized, ct_size_info.fixed_size must be set!") as usize
-            }
-        };
-        let signature = &signature[idx..idx + signature_length];
-        let verified = scheme_impl.verify_message(
-            &message,
-            &signature,
-            parsed_combined_public_key
-                .id_mapping
-                .get(&scheme_id)
-                .expect(
-                    format!(
-                        "Public key not with id {} and config {} not found!",
-                        scheme_id.0, scheme_id.1
-                    )
-                        .as_str(),
-                ),
-        );
-        if !verified {
-            return false;
-        }
-        idx += signature_length;
-    }
-    return true;
-}
+use crate::hybrid_signature::{parse_combined_public_key, ComponentEncoding};
+use crate::hybrid_signature::types::component_scheme_ids;
+use crate::hybrid_signature::seed_parser::parse_seed;
+use crate::schemes::signature::get_id_to_ref_mapping;
+use crate::hybrid_signature::sized_field::{decode_sized_field, encode_sized_field};
+use crate::schemes::{AlgorithmPurpose, Keypair};
+use log::error;
+use rand_core::CryptoRngCore;
+
+pub fn sign_message(seed: &[u8], message: &[u8]) -> Result<Vec<u8>, String> {
+    let (encoding, keypairs) = seed_keypairs(seed)?;
+    return sign_message_with_keypairs(&keypairs, encoding, message, None);
+}
+
+// Randomized schemes draw their signing randomness from rng, fails if a scheme can't
+pub fn sign_message_with_rng(seed: &[u8], message: &[u8], rng: &mut dyn CryptoRngCore) -> Result<Vec<u8>, String> {
+    let (encoding, keypairs) = seed_keypairs(seed)?;
+    return sign_message_with_keypairs(&keypairs, encoding, message, Some(rng));
+}
+
+type ComponentKeypairs = Vec<((u16, u16), Keypair)>;
+
+fn seed_keypairs(seed: &[u8]) -> Result<(ComponentEncoding, ComponentKeypairs), String> {
+    let mapping = get_id_to_ref_mapping();
+    let parsed_seed = parse_seed(&seed)?;
+    let mut keypairs = ComponentKeypairs::new();
+    for scheme_id in parsed_seed.signature_scheme_ids.iter() {
+        let scheme_impl = match mapping.get(scheme_id) {
+            Some(scheme_impl) => scheme_impl,
+            None => {
+                return Err(format!(
+                    "Algorithm not with id {} and config {} not found!",
+                    scheme_id.0, scheme_id.1
+                ));
+            }
+        };
+        let seed_branch = parsed_seed.scheme_seed_branch(AlgorithmPurpose::Signature, *scheme_id)?;
+        // Generate keypair
+        keypairs.push((*scheme_id, scheme_impl.generate_keypair(&seed_branch)));
+    }
+    // Signatures follow the component order of the seed's public key
+    if parsed_seed.encoding() == ComponentEncoding::Varint {
+        keypairs.sort_by_key(|(scheme_id, _)| *scheme_id);
+    }
+    return Ok((parsed_seed.encoding(), keypairs));
+}
+
+// Signs with every keypair in the given order, whether it was derived from a seed or supplied.
+// The signature uses the scheme id encoding of the key it verifies under.
+// Without an rng randomized schemes use the backend's own randomness.
+pub(crate) fn sign_message_with_keypairs(keypairs: &[((u16, u16), Keypair)], encoding: ComponentEncoding, message: &[u8], mut rng: Option<&mut dyn CryptoRngCore>) -> Result<Vec<u8>, String> {
+    let mapping = get_id_to_ref_mapping();
+    let mut message_signatures = Vec::<u8>::new();
+    for (scheme_id, keypair) in keypairs.iter() {
+        let scheme_impl = match mapping.get(scheme_id) {
+            Some(scheme_impl) => scheme_impl,
+            None => {
+                return Err(format!(
+                    "Algorithm with id {} and config {} not found!",
+                    scheme_id.0, scheme_id.1
+                ));
+            }
+        };
+
+        // Start by pushing the scheme id and configuration
+        encoding.encode_scheme_id(*scheme_id, &mut message_signatures)?;
+
+        // Sign said message
+        let signature = match rng.as_mut() {
+            Some(rng) => scheme_impl.sign_message_with_rng(&keypair.sk, message, *rng)?,
+            None => scheme_impl.sign_message(&keypair.sk, message)?,
+        };
+        // Add size data
+        let scheme_info = scheme_impl.get_scheme_info();
+        if let Err(e) = encode_sized_field(&mut message_signatures, encoding, &signature, &scheme_info.ct_size_info) {
+            return Err(format!(
+                "Signature of algorithm with id {} and config {} can't be encoded: {}",
+                scheme_id.0, scheme_id.1, e
+            ));
+        }
+    }
+    return Ok(message_signatures);
+}
+
+pub fn verify_message(message: &[u8], combined_public_key: &[u8], signature: &[u8]) -> bool {
+    let mapping = get_id_to_ref_mapping();
+    let mut idx: usize = 0;
+    let parsed_combined_public_key = match parse_combined_public_key(AlgorithmPurpose::Signature, &combined_public_key) {
+        Ok(pk) => {
+            pk
+        }
+        Err(e) => {
+            error!("Error parsing public key: {}... verify_message will return false!", e);
+            return false;
+        }
+    };
+    while idx < signature.len() {
+        // Get the ID and configuration
+        let scheme_id = match parsed_combined_public_key.encoding.decode_scheme_id(signature, &mut idx) {
+            Ok(scheme_id) => scheme_id,
+            Err(e) => {
+                error!("Error parsing signature: {}... verify_message will return false!", e);
+                return false;
+            }
+        };
+        let scheme_impl = match mapping.get(&scheme_id) {
+            Some(scheme_impl) => scheme_impl,
+            None => {
+                error!(
+                    "Algorithm with id {} and config {} not found... verify_message will return false!",
+                    scheme_id.0, scheme_id.1
+                );
+                return false;
+            }
+        };
+        let scheme_info = scheme_impl.get_scheme_info();
+        let signature = match decode_sized_field(signature, &mut idx, parsed_combined_public_key.encoding, &scheme_info.ct_size_info) {
+            Ok(signature) => signature,
+            Err(e) => {
+                error!("Error parsing signature: {}... verify_message will return false!", e);
+                return false;
+            }
+        };
+        let public_key = match parsed_combined_public_key.get(&scheme_id) {
+            Some(public_key) => public_key,
+            None => {
+                error!(
+                    "Public key with id {} and config {} not found... verify_message will return false!",
+                    scheme_id.0, scheme_id.1
+                );
+                return false;
+            }
+        };
+        let verified = scheme_impl.verify_message(&message, &signature, public_key);
+        if !verified {
+            return false;
+        }
+    }
+    return true;
+}
+
+// Like verify_message, but the signature has to hold exactly one component for every component of
+// the public key, so a signature stripped down to a subset of its schemes (or to nothing) fails
+pub fn verify_message_strict(message: &[u8], combined_public_key: &[u8], signature: &[u8]) -> bool {
+    let parsed_combined_public_key = match parse_combined_public_key(AlgorithmPurpose::Signature, &combined_public_key) {
+        Ok(pk) => pk,
+        Err(e) => {
+            error!("Error parsing public key: {}... verify_message_strict will return false!", e);
+            return false;
+        }
+    };
+    let mut signed_scheme_ids = match component_scheme_ids(AlgorithmPurpose::Signature, parsed_combined_public_key.encoding, signature) {
+        Ok(scheme_ids) => scheme_ids,
+        Err(e) => {
+            error!("Error parsing signature: {}... verify_message_strict will return false!", e);
+            return false;
+        }
+    };
+    let mut scheme_ids = parsed_combined_public_key.scheme_ids();
+    signed_scheme_ids.sort();
+    scheme_ids.sort();
+    if signed_scheme_ids != scheme_ids {
+        error!("Signature does not cover every component of the public key... verify_message_strict will return false!");
+        return false;
+    }
+    return verify_message(message, combined_public_key, signature);
+}
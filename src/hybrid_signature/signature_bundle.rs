@@ -0,0 +1,79 @@
+use crate::hybrid_signature::{HybridSignature, HybridSigner, SignatureAddress, SignaturePublicKey};
+use crate::varint::{decode_varint, encode_varint};
+use std::fmt;
+
+// Signature together with the combined public key that made it, so a verifier that only stores
+// addresses doesn't have to look the key up elsewhere:
+// public key length (varint) || combined public key || signature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureWithPublicKey {
+    pub public_key: SignaturePublicKey,
+    pub signature: HybridSignature,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AddressVerificationError {
+    // The embedded public key doesn't hash to the address
+    AddressMismatch,
+    // The public key matches, but the signature doesn't verify under it
+    InvalidSignature,
+    // Fewer distinct signers than a multisig address requires
+    ThresholdNotMet,
+    // A Merkle signature doesn't disclose the components the verifier requires
+    MissingComponents,
+}
+
+impl fmt::Display for AddressVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            AddressVerificationError::AddressMismatch => write!(f, "Public key does not match the address"),
+            AddressVerificationError::InvalidSignature => write!(f, "Signature does not verify under the public key"),
+            AddressVerificationError::ThresholdNotMet => write!(f, "Not enough signers signed"),
+            AddressVerificationError::MissingComponents => write!(f, "Required public key components were not disclosed"),
+        };
+    }
+}
+
+impl std::error::Error for AddressVerificationError {}
+
+impl SignatureWithPublicKey {
+    pub fn sign<S: HybridSigner>(signer: &S, message: &[u8]) -> Result<Self, String> {
+        return Ok(SignatureWithPublicKey {
+            public_key: signer.signature_public_key()?,
+            signature: signer.sign(message)?,
+        });
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_varint(self.public_key.as_bytes().len() as u64, &mut out);
+        out.extend(self.public_key.as_bytes());
+        out.extend(self.signature.as_bytes());
+        return out;
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut idx: usize = 0;
+        let public_key_len = decode_varint(bytes, &mut idx)?;
+        if public_key_len > (bytes.len() - idx) as u64 {
+            return Err("Signature bundle is truncated".to_string());
+        }
+        let signature_start = idx + public_key_len as usize;
+        return Ok(SignatureWithPublicKey {
+            public_key: SignaturePublicKey::from_bytes(&bytes[idx..signature_start])?,
+            signature: HybridSignature::from_bytes(&bytes[signature_start..])?,
+        });
+    }
+}
+
+// Checks the embedded public key against the address before verifying. Verification is strict,
+// so the signature has to cover every component of the key the address commits to.
+pub fn verify_with_address(message: &[u8], bundle: &SignatureWithPublicKey, address: &SignatureAddress) -> Result<(), AddressVerificationError> {
+    if bundle.public_key.address() != *address {
+        return Err(AddressVerificationError::AddressMismatch);
+    }
+    if !bundle.public_key.verify(message, &bundle.signature) {
+        return Err(AddressVerificationError::InvalidSignature);
+    }
+    return Ok(());
+}
@@ -0,0 +1,172 @@
+use crate::hybrid_signature::ComponentEncoding;
+use crate::schemes::{SizeInfo, SizeKind};
+use crate::varint::{decode_varint, encode_varint};
+
+#[allow(deprecated)]
+fn legacy_length_prefix_len(size_info: &SizeInfo) -> Result<usize, String> {
+    return match size_info.variable_size_bytelen {
+        Some(bytelen) if (1..=8).contains(&bytelen) => Ok(bytelen as usize),
+        _ => Err("Variable sized field has no legacy length prefix".to_string()),
+    };
+}
+
+pub(crate) fn check_field_size(field: &[u8], size_info: &SizeInfo) -> Result<(), String> {
+    match size_info.kind {
+        SizeKind::FixedSized => {
+            let fixed_size = size_info.fixed_size.expect("When kind == SizeKind::FixedSized, fixed_size must be set!") as usize;
+            if field.len() != fixed_size {
+                return Err(format!("Field is {} bytes but must be exactly {} bytes", field.len(), fixed_size));
+            }
+        }
+        SizeKind::VariableSized => {
+            let max_size = size_info.max_size.expect("When kind == SizeKind::VariableSized, max_size must be set!") as usize;
+            if field.len() > max_size {
+                return Err(format!("Field is {} bytes but may be at most {} bytes", field.len(), max_size));
+            }
+        }
+    }
+    return Ok(());
+}
+
+// Variable sized fields are prefixed with their length as a varint, which can't truncate, or in
+// the legacy encoding as a little endian number of variable_size_bytelen bytes. Fixed sized
+// fields are written as is. Either way the field has to fit the size info.
+pub(crate) fn encode_sized_field(out: &mut Vec<u8>, encoding: ComponentEncoding, field: &[u8], size_info: &SizeInfo) -> Result<(), String> {
+    check_field_size(field, size_info)?;
+    match size_info.kind {
+        SizeKind::FixedSized => {}
+        SizeKind::VariableSized => {
+            match encoding {
+                ComponentEncoding::Legacy => {
+                    let prefix_len = legacy_length_prefix_len(size_info)?;
+                    let le_bytes = (field.len() as u64).to_le_bytes();
+                    if le_bytes[prefix_len..].iter().any(|byte| *byte != 0) {
+                        return Err(format!("Field is {} bytes, too long for a {} byte length prefix", field.len(), prefix_len));
+                    }
+                    out.extend(&le_bytes[..prefix_len]);
+                }
+                ComponentEncoding::Varint => encode_varint(field.len() as u64, out),
+            }
+        }
+    }
+    out.extend(field);
+    return Ok(());
+}
+
+// Reads a field written by encode_sized_field. Announced lengths above the maximum size are
+// rejected before anything is sliced or allocated.
+pub(crate) fn decode_sized_field<'a>(bytes: &'a [u8], idx: &mut usize, encoding: ComponentEncoding, size_info: &SizeInfo) -> Result<&'a [u8], String> {
+    let field_length = match size_info.kind {
+        SizeKind::FixedSized => {
+            size_info.fixed_size.expect("When kind == SizeKind::FixedSized, fixed_size must be set!") as usize
+        }
+        SizeKind::VariableSized => {
+            let max_size = size_info.max_size.expect("When kind == SizeKind::VariableSized, max_size must be set!") as u64;
+            let field_length = match encoding {
+                ComponentEncoding::Legacy => {
+                    let prefix_len = legacy_length_prefix_len(size_info)?;
+                    let prefix = match bytes.get(*idx..*idx + prefix_len) {
+                        Some(prefix) => prefix,
+                        None => return Err("Field length is truncated".to_string()),
+                    };
+                    *idx += prefix_len;
+                    let mut le_bytes = [0u8; 8];
+                    le_bytes[..prefix_len].copy_from_slice(prefix);
+                    u64::from_le_bytes(le_bytes)
+                }
+                ComponentEncoding::Varint => decode_varint(bytes, idx)?,
+            };
+            if field_length > max_size {
+                return Err(format!("Field announces {} bytes but may be at most {} bytes", field_length, max_size));
+            }
+            field_length as usize
+        }
+    };
+    let field = match bytes.get(*idx..*idx + field_length) {
+        Some(field) => field,
+        None => return Err("Field is truncated".to_string()),
+    };
+    *idx += field_length;
+    return Ok(field);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hybrid_signature::ComponentEncoding::{Legacy, Varint};
+
+    #[allow(deprecated)]
+    const VARIABLE: SizeInfo = SizeInfo {
+        kind: SizeKind::VariableSized,
+        fixed_size: None,
+        max_size: Some(300),
+        variable_size_bytelen: Some(2),
+    };
+    #[allow(deprecated)]
+    const FIXED: SizeInfo = SizeInfo {
+        kind: SizeKind::FixedSized,
+        fixed_size: Some(4),
+        max_size: None,
+        variable_size_bytelen: None,
+    };
+
+    #[test]
+    fn test_sized_field_round_trip() {
+        for encoding in [Legacy, Varint] {
+            for field in [vec![], vec![1u8; 127], vec![2u8; 128], vec![3u8; 300]] {
+                let mut encoded = Vec::new();
+                encode_sized_field(&mut encoded, encoding, &field, &VARIABLE).unwrap();
+                let mut idx = 0;
+                assert_eq!(decode_sized_field(&encoded, &mut idx, encoding, &VARIABLE), Ok(&field[..]));
+                assert_eq!(idx, encoded.len());
+            }
+            let mut encoded = Vec::new();
+            encode_sized_field(&mut encoded, encoding, &[1, 2, 3, 4], &FIXED).unwrap();
+            assert_eq!(encoded, vec![1, 2, 3, 4]);
+        }
+        // Legacy fields keep their little endian length prefix
+        let mut encoded = Vec::new();
+        encode_sized_field(&mut encoded, Legacy, &[3u8; 300], &VARIABLE).unwrap();
+        assert_eq!(&encoded[..2], &[0x2c, 0x01]);
+        let mut encoded = Vec::new();
+        encode_sized_field(&mut encoded, Varint, &[3u8; 300], &VARIABLE).unwrap();
+        assert_eq!(&encoded[..2], &[0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_sized_field_limits() {
+        for encoding in [Legacy, Varint] {
+            let mut encoded = Vec::new();
+            assert!(encode_sized_field(&mut encoded, encoding, &[0u8; 301], &VARIABLE).is_err());
+            assert!(encode_sized_field(&mut encoded, encoding, &[0u8; 3], &FIXED).is_err());
+            assert!(encode_sized_field(&mut encoded, encoding, &[0u8; 5], &FIXED).is_err());
+            assert!(encoded.is_empty());
+            assert!(decode_sized_field(&[5, 0, 0], &mut 0, encoding, &VARIABLE).is_err());
+            assert!(decode_sized_field(&[0, 0, 0], &mut 0, encoding, &FIXED).is_err());
+        }
+
+        // A length above the maximum is refused even though the bytes are there
+        let mut oversized = Vec::new();
+        encode_varint(301, &mut oversized);
+        oversized.extend([0u8; 301]);
+        assert!(decode_sized_field(&oversized, &mut 0, Varint, &VARIABLE).is_err());
+        let mut oversized = vec![0x2d, 0x01];
+        oversized.extend([0u8; 301]);
+        assert!(decode_sized_field(&oversized, &mut 0, Legacy, &VARIABLE).is_err());
+        // A huge length doesn't get near an allocation
+        let mut huge = Vec::new();
+        encode_varint(u64::MAX, &mut huge);
+        assert!(decode_sized_field(&huge, &mut 0, Varint, &VARIABLE).is_err());
+        assert!(decode_sized_field(&[0xff, 0xff], &mut 0, Legacy, &VARIABLE).is_err());
+        assert!(decode_sized_field(&[1], &mut 0, Legacy, &VARIABLE).is_err());
+        // Without a prefix length there is no legacy encoding
+        #[allow(deprecated)]
+        let without_prefix = SizeInfo {
+            variable_size_bytelen: None,
+            ..VARIABLE
+        };
+        assert!(encode_sized_field(&mut Vec::new(), Legacy, &[0u8; 3], &without_prefix).is_err());
+        assert!(encode_sized_field(&mut Vec::new(), Varint, &[0u8; 3], &without_prefix).is_ok());
+    }
+}
@@ -0,0 +1,352 @@
+use crate::hybrid_signature::sized_field::decode_sized_field;
+use crate::hybrid_signature::ComponentEncoding;
+use crate::hybrid_signature::{decapsulate, encapsulate, encapsulate_with_rng, generate_combined_public_key, generate_random_seed, hash_combined_public_key, KeyGenerationOptions};
+use crate::hybrid_signature::{parse_combined_public_key, parse_seed, sign_message, sign_message_with_rng, verify_message, verify_message_strict, wrap_seed, ParsedCombinedPublicKey, ParsedSeed};
+use crate::scheme_info_mapping::get_id_to_info_mapping;
+use crate::schemes::{key_encapsulation, signature};
+use crate::schemes::AlgorithmPurpose;
+use crate::secret::SecretBytes;
+use rand_core::CryptoRngCore;
+use std::marker::PhantomData;
+
+const SHARED_SECRET_LEN: usize = 32;
+const ADDRESS_LEN: usize = 32;
+
+// Ties a combined public key or address to its purpose at the type level
+pub trait KeyPurpose {
+    const PURPOSE: AlgorithmPurpose;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignaturePurpose;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEncapsulationPurpose;
+
+impl KeyPurpose for SignaturePurpose {
+    const PURPOSE: AlgorithmPurpose = AlgorithmPurpose::Signature;
+}
+
+impl KeyPurpose for KeyEncapsulationPurpose {
+    const PURPOSE: AlgorithmPurpose = AlgorithmPurpose::KeyEncapsulation;
+}
+
+pub type SignaturePublicKey = CombinedPublicKey<SignaturePurpose>;
+pub type KeyEncapsulationPublicKey = CombinedPublicKey<KeyEncapsulationPurpose>;
+pub type SignatureAddress = Address<SignaturePurpose>;
+pub type KeyEncapsulationAddress = Address<KeyEncapsulationPurpose>;
+
+// Checks that bytes are a sequence of scheme id || sized field components, as produced for
+// signatures and ciphertexts, and returns the scheme ids in order
+pub(crate) fn component_scheme_ids(purpose: AlgorithmPurpose, encoding: ComponentEncoding, bytes: &[u8]) -> Result<Vec<(u16, u16)>, String> {
+    let mapping = get_id_to_info_mapping(purpose);
+    let mut scheme_ids = Vec::new();
+    let mut idx: usize = 0;
+    while idx < bytes.len() {
+        let scheme_id = encoding.decode_scheme_id(bytes, &mut idx)?;
+        let scheme_info = match mapping.get(&scheme_id) {
+            Some(scheme_info) => scheme_info,
+            None => {
+                return Err(format!(
+                    "Algorithm with id {} and config {} not found!",
+                    scheme_id.0, scheme_id.1
+                ));
+            }
+        };
+        decode_sized_field(bytes, &mut idx, encoding, &scheme_info.ct_size_info)?;
+        scheme_ids.push(scheme_id);
+    }
+    return Ok(scheme_ids);
+}
+
+// Signatures and ciphertexts don't say which encoding they use, so accept either
+fn any_component_scheme_ids(purpose: AlgorithmPurpose, bytes: &[u8]) -> Result<Vec<(u16, u16)>, String> {
+    return match component_scheme_ids(purpose, ComponentEncoding::Varint, bytes) {
+        Ok(scheme_ids) => Ok(scheme_ids),
+        Err(e) => match component_scheme_ids(purpose, ComponentEncoding::Legacy, bytes) {
+            Ok(scheme_ids) => Ok(scheme_ids),
+            Err(_) => Err(e),
+        },
+    };
+}
+
+// Secret material is wrapped in SecretBytes, so it is zeroized on drop, redacted in Debug
+// output and compared in constant time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HybridSeed(SecretBytes);
+
+// Seeds may name schemes that aren't compiled in, which parse_seed can't tell
+fn check_seed_schemes(parsed_seed: &ParsedSeed) -> Result<(), String> {
+    let signature_mapping = signature::get_id_to_ref_mapping();
+    for scheme_id in parsed_seed.signature_scheme_ids.iter() {
+        if !signature_mapping.contains_key(scheme_id) {
+            return Err(format!("Signature algorithm with id {} and config {} not found!", scheme_id.0, scheme_id.1));
+        }
+    }
+    let key_encapsulation_mapping = key_encapsulation::get_id_to_ref_mapping();
+    for scheme_id in parsed_seed.key_encapsulation_scheme_ids.iter() {
+        if !key_encapsulation_mapping.contains_key(scheme_id) {
+            return Err(format!("Key encapsulation algorithm with id {} and config {} not found!", scheme_id.0, scheme_id.1));
+        }
+    }
+    return Ok(());
+}
+
+impl HybridSeed {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        check_seed_schemes(&parse_seed(bytes)?)?;
+        return Ok(HybridSeed(bytes.to_vec().into()));
+    }
+
+    pub fn generate(signature_algorithms: &[String], key_encapsulation_algorithms: &[String]) -> Result<Self, String> {
+        return Ok(HybridSeed(generate_random_seed(signature_algorithms, key_encapsulation_algorithms)?.into()));
+    }
+
+    pub fn wrap(signature_algorithms: &[String], key_encapsulation_algorithms: &[String], entropy: &[u8]) -> Result<Self, String> {
+        return Ok(HybridSeed(wrap_seed(signature_algorithms, key_encapsulation_algorithms, entropy)?.into()));
+    }
+
+    pub fn parse(&self) -> ParsedSeed {
+        return parse_seed(&self.0).expect("HybridSeed holds a seed that was already parsed");
+    }
+
+    pub fn public_key<P: KeyPurpose>(&self) -> Result<CombinedPublicKey<P>, String> {
+        return Ok(CombinedPublicKey {
+            bytes: generate_combined_public_key(P::PURPOSE, &self.0, KeyGenerationOptions::default())?,
+            purpose: PhantomData,
+        });
+    }
+
+    pub fn signature_public_key(&self) -> Result<SignaturePublicKey, String> {
+        return self.public_key();
+    }
+
+    pub fn key_encapsulation_public_key(&self) -> Result<KeyEncapsulationPublicKey, String> {
+        return self.public_key();
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<HybridSignature, String> {
+        return Ok(HybridSignature(sign_message(&self.0, message)?));
+    }
+
+    pub fn sign_with_rng(&self, message: &[u8], rng: &mut dyn CryptoRngCore) -> Result<HybridSignature, String> {
+        return Ok(HybridSignature(sign_message_with_rng(&self.0, message, rng)?));
+    }
+
+    pub fn decapsulate(&self, ciphertext: &HybridCiphertext) -> Result<SharedSecret, String> {
+        return decapsulate(&self.0, &ciphertext.0);
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+// Anything that holds signature secret keys, so a seed and a key bundle can be used alike
+pub trait HybridSigner {
+    fn signature_public_key(&self) -> Result<SignaturePublicKey, String>;
+    fn sign(&self, message: &[u8]) -> Result<HybridSignature, String>;
+}
+
+// Anything that holds key encapsulation secret keys
+pub trait HybridDecapsulator {
+    fn key_encapsulation_public_key(&self) -> Result<KeyEncapsulationPublicKey, String>;
+    fn decapsulate(&self, ciphertext: &HybridCiphertext) -> Result<SharedSecret, String>;
+}
+
+impl HybridSigner for HybridSeed {
+    fn signature_public_key(&self) -> Result<SignaturePublicKey, String> {
+        return HybridSeed::signature_public_key(self);
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<HybridSignature, String> {
+        return HybridSeed::sign(self, message);
+    }
+}
+
+impl HybridDecapsulator for HybridSeed {
+    fn key_encapsulation_public_key(&self) -> Result<KeyEncapsulationPublicKey, String> {
+        return HybridSeed::key_encapsulation_public_key(self);
+    }
+
+    fn decapsulate(&self, ciphertext: &HybridCiphertext) -> Result<SharedSecret, String> {
+        return HybridSeed::decapsulate(self, ciphertext);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CombinedPublicKey<P: KeyPurpose> {
+    bytes: Vec<u8>,
+    purpose: PhantomData<P>,
+}
+
+impl<P: KeyPurpose> CombinedPublicKey<P> {
+    // Accepts keys with or without header, as long as they are meant for P
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        parse_combined_public_key(P::PURPOSE, bytes)?;
+        return Ok(CombinedPublicKey {
+            bytes: bytes.to_vec(),
+            purpose: PhantomData,
+        });
+    }
+
+    pub fn purpose(&self) -> AlgorithmPurpose {
+        return P::PURPOSE;
+    }
+
+    pub fn parse(&self) -> ParsedCombinedPublicKey {
+        return parse_combined_public_key(P::PURPOSE, &self.bytes).expect("CombinedPublicKey holds a key that was already parsed");
+    }
+
+    pub fn address(&self) -> Address<P> {
+        return Address {
+            bytes: hash_combined_public_key(&self.bytes).expect("CombinedPublicKey holds a key that was already parsed"),
+            purpose: PhantomData,
+        };
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.bytes;
+    }
+}
+
+impl CombinedPublicKey<SignaturePurpose> {
+    // Every component of the key has to sign, and a key without components verifies nothing
+    pub fn verify(&self, message: &[u8], signature: &HybridSignature) -> bool {
+        if signature.0.is_empty() {
+            return false;
+        }
+        return verify_message_strict(message, &self.bytes, &signature.0);
+    }
+
+    // Accepts signatures by a subset of the components, even an empty one, see verify_message
+    pub fn verify_lenient(&self, message: &[u8], signature: &HybridSignature) -> bool {
+        return verify_message(message, &self.bytes, &signature.0);
+    }
+}
+
+impl CombinedPublicKey<KeyEncapsulationPurpose> {
+    pub fn encapsulate(&self) -> Result<(SharedSecret, HybridCiphertext), String> {
+        let (ss, ct) = encapsulate(&self.bytes)?;
+        return Ok((ss, HybridCiphertext(ct)));
+    }
+
+    pub fn encapsulate_with_rng(&self, rng: &mut dyn CryptoRngCore) -> Result<(SharedSecret, HybridCiphertext), String> {
+        let (ss, ct) = encapsulate_with_rng(&self.bytes, rng)?;
+        return Ok((ss, HybridCiphertext(ct)));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HybridSignature(Vec<u8>);
+
+impl HybridSignature {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        any_component_scheme_ids(AlgorithmPurpose::Signature, bytes)?;
+        return Ok(HybridSignature(bytes.to_vec()));
+    }
+
+    pub fn scheme_ids(&self) -> Vec<(u16, u16)> {
+        return any_component_scheme_ids(AlgorithmPurpose::Signature, &self.0).expect("HybridSignature holds a signature that was already parsed");
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HybridCiphertext(Vec<u8>);
+
+impl HybridCiphertext {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        any_component_scheme_ids(AlgorithmPurpose::KeyEncapsulation, bytes)?;
+        return Ok(HybridCiphertext(bytes.to_vec()));
+    }
+
+    pub fn scheme_ids(&self) -> Vec<(u16, u16)> {
+        return any_component_scheme_ids(AlgorithmPurpose::KeyEncapsulation, &self.0).expect("HybridCiphertext holds a ciphertext that was already parsed");
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedSecret(pub(crate) SecretBytes);
+
+impl SharedSecret {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != SHARED_SECRET_LEN {
+            return Err(format!("Shared secret must be {} bytes", SHARED_SECRET_LEN));
+        }
+        return Ok(SharedSecret(bytes.to_vec().into()));
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Address<P: KeyPurpose> {
+    bytes: Vec<u8>,
+    purpose: PhantomData<P>,
+}
+
+impl<P: KeyPurpose> Address<P> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != ADDRESS_LEN {
+            return Err(format!("Address must be {} bytes", ADDRESS_LEN));
+        }
+        return Ok(Address {
+            bytes: bytes.to_vec(),
+            purpose: PhantomData,
+        });
+    }
+
+    pub fn purpose(&self) -> AlgorithmPurpose {
+        return P::PURPOSE;
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.bytes;
+    }
+}
+
+impl AsRef<[u8]> for HybridSeed {
+    fn as_ref(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl<P: KeyPurpose> AsRef<[u8]> for CombinedPublicKey<P> {
+    fn as_ref(&self) -> &[u8] {
+        return &self.bytes;
+    }
+}
+
+impl AsRef<[u8]> for HybridSignature {
+    fn as_ref(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl AsRef<[u8]> for HybridCiphertext {
+    fn as_ref(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl AsRef<[u8]> for SharedSecret {
+    fn as_ref(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl<P: KeyPurpose> AsRef<[u8]> for Address<P> {
+    fn as_ref(&self) -> &[u8] {
+        return &self.bytes;
+    }
+}
@@ -0,0 +1,323 @@
+use crate::hybrid_signature::{CombinedPublicKey, HybridSignature, HybridSigner, KeyPurpose, SignaturePublicKey};
+use crate::schemes::{signature, AlgorithmPurpose};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+
+// Hybrid combinations have no IANA registered "alg" values, so they get names from the private
+// "HYB-" space: the registry names of the components in canonical order, joined by "+"
+pub const JWS_ALGORITHM_PREFIX: &str = "HYB-";
+pub const JWK_KEY_TYPE: &str = "HYB";
+
+pub fn jws_algorithm(public_key: &SignaturePublicKey) -> String {
+    let mapping = signature::get_name_to_ref_mapping();
+    let mut scheme_ids = public_key.parse().scheme_ids();
+    scheme_ids.sort();
+    let mut names = Vec::<String>::new();
+    for scheme_id in scheme_ids.iter() {
+        let name = mapping
+            .iter()
+            .find(|(_, scheme_ref)| (scheme_ref.scheme_id, scheme_ref.scheme_config_id) == *scheme_id)
+            .map(|(name, _)| name.clone())
+            .expect("Combined public keys only hold registered schemes");
+        names.push(name);
+    }
+    return format!("{}{}", JWS_ALGORITHM_PREFIX, names.join("+"));
+}
+
+fn base64url_decode(value: &str, what: &str) -> Result<Vec<u8>, String> {
+    return URL_SAFE_NO_PAD.decode(value).map_err(|_| format!("{} is not valid base64url", what));
+}
+
+fn string_member<'a>(object: &'a Map<String, Value>, member: &str) -> Result<Option<&'a str>, String> {
+    return match object.get(member) {
+        None => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(format!("Member {} must be a string", member)),
+    };
+}
+
+fn jwk_use(purpose: AlgorithmPurpose) -> &'static str {
+    return match purpose {
+        AlgorithmPurpose::Signature => "sig",
+        AlgorithmPurpose::KeyEncapsulation => "enc",
+    };
+}
+
+impl<P: KeyPurpose> CombinedPublicKey<P> {
+    // The key id is the base64url address, so a JWS "kid" points straight at the key
+    pub fn jwk_key_id(&self) -> String {
+        return URL_SAFE_NO_PAD.encode(self.address().as_bytes());
+    }
+
+    pub fn to_jwk(&self) -> String {
+        let mut jwk = json!({
+            "kty": JWK_KEY_TYPE,
+            "use": jwk_use(P::PURPOSE),
+            "kid": self.jwk_key_id(),
+            "pub": URL_SAFE_NO_PAD.encode(self.as_bytes()),
+        });
+        if P::PURPOSE == AlgorithmPurpose::Signature {
+            let public_key = SignaturePublicKey::from_bytes(self.as_bytes()).expect("Purpose was checked");
+            jwk["alg"] = Value::String(jws_algorithm(&public_key));
+        }
+        return jwk.to_string();
+    }
+
+    pub fn from_jwk(jwk: &str) -> Result<Self, String> {
+        let jwk: Value = serde_json::from_str(jwk).map_err(|e| format!("Invalid JSON Web Key: {}", e))?;
+        let jwk = match jwk.as_object() {
+            Some(jwk) => jwk,
+            None => return Err("JSON Web Key must be an object".to_string()),
+        };
+        if string_member(jwk, "kty")? != Some(JWK_KEY_TYPE) {
+            return Err(format!("Expected a JSON Web Key of type {}", JWK_KEY_TYPE));
+        }
+        if let Some(key_use) = string_member(jwk, "use")? {
+            if key_use != jwk_use(P::PURPOSE) {
+                return Err(format!("JSON Web Key is for use {}, not {}", key_use, jwk_use(P::PURPOSE)));
+            }
+        }
+        let public_key = match string_member(jwk, "pub")? {
+            Some(public_key) => CombinedPublicKey::<P>::from_bytes(&base64url_decode(public_key, "Member pub")?)?,
+            None => return Err("JSON Web Key has no pub member".to_string()),
+        };
+        // Optional members have to agree with the key they describe
+        if let Some(key_id) = string_member(jwk, "kid")? {
+            if key_id != public_key.jwk_key_id() {
+                return Err("JSON Web Key id does not match its public key".to_string());
+            }
+        }
+        if let Some(algorithm) = string_member(jwk, "alg")? {
+            let signature_public_key = SignaturePublicKey::from_bytes(public_key.as_bytes());
+            if signature_public_key.map(|pk| jws_algorithm(&pk)).as_deref() != Ok(algorithm) {
+                return Err(format!("JSON Web Key algorithm {} does not match its public key", algorithm));
+            }
+        }
+        return Ok(public_key);
+    }
+}
+
+fn protected_header(public_key: &SignaturePublicKey) -> String {
+    let header = json!({
+        "alg": jws_algorithm(public_key),
+        "kid": public_key.jwk_key_id(),
+    });
+    return URL_SAFE_NO_PAD.encode(header.to_string());
+}
+
+fn sign_jws<S: HybridSigner>(signer: &S, payload: &[u8]) -> Result<(String, String, String), String> {
+    let public_key = signer.signature_public_key()?;
+    if public_key.parse().components.is_empty() {
+        return Err("JWS signers need at least one signature scheme".to_string());
+    }
+    let protected = protected_header(&public_key);
+    let payload = URL_SAFE_NO_PAD.encode(payload);
+    let signing_input = format!("{}.{}", protected, payload);
+    let signature = URL_SAFE_NO_PAD.encode(signer.sign(signing_input.as_bytes())?.as_bytes());
+    return Ok((protected, payload, signature));
+}
+
+// Checks the protected header and the signature over it, and returns the decoded payload.
+// The algorithm is taken from the key, never from the header, and every component has to sign.
+fn verify_jws(public_key: &SignaturePublicKey, protected: &str, payload: &str, signature: &str) -> Result<Vec<u8>, String> {
+    // Neither a key without components nor an empty signature proves anything
+    if public_key.parse().components.is_empty() {
+        return Err("JWS public key has no components".to_string());
+    }
+    if signature.is_empty() {
+        return Err("JWS signature is empty".to_string());
+    }
+    let header: Value = serde_json::from_slice(&base64url_decode(protected, "Protected header")?).map_err(|e| format!("Invalid protected header: {}", e))?;
+    let header = match header.as_object() {
+        Some(header) => header,
+        None => return Err("Protected header must be an object".to_string()),
+    };
+    let algorithm = jws_algorithm(public_key);
+    if string_member(header, "alg")? != Some(algorithm.as_str()) {
+        return Err(format!("Protected header algorithm is not {}", algorithm));
+    }
+    if header.contains_key("crit") {
+        return Err("Critical header parameters are not supported".to_string());
+    }
+    if let Some(key_id) = string_member(header, "kid")? {
+        if key_id != public_key.jwk_key_id() {
+            return Err("Protected header key id does not match the public key".to_string());
+        }
+    }
+    let signature = HybridSignature::from_bytes(&base64url_decode(signature, "Signature")?)?;
+    let signing_input = format!("{}.{}", protected, payload);
+    if !public_key.verify(signing_input.as_bytes(), &signature) {
+        return Err("Signature verification failed".to_string());
+    }
+    return base64url_decode(payload, "Payload");
+}
+
+pub fn jws_sign_compact<S: HybridSigner>(signer: &S, payload: &[u8]) -> Result<String, String> {
+    let (protected, payload, signature) = sign_jws(signer, payload)?;
+    return Ok(format!("{}.{}.{}", protected, payload, signature));
+}
+
+pub fn jws_verify_compact(public_key: &SignaturePublicKey, token: &str) -> Result<Vec<u8>, String> {
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return Err("Compact JWS must have three parts".to_string());
+    }
+    return verify_jws(public_key, parts[0], parts[1], parts[2]);
+}
+
+// Flattened JWS JSON serialization
+pub fn jws_sign_json<S: HybridSigner>(signer: &S, payload: &[u8]) -> Result<String, String> {
+    let (protected, payload, signature) = sign_jws(signer, payload)?;
+    let jws = json!({
+        "payload": payload,
+        "protected": protected,
+        "signature": signature,
+    });
+    return Ok(jws.to_string());
+}
+
+// Accepts the flattened and the general JSON serialization; with several signatures, the first
+// one made by public_key has to verify
+pub fn jws_verify_json(public_key: &SignaturePublicKey, jws: &str) -> Result<Vec<u8>, String> {
+    let jws: Value = serde_json::from_str(jws).map_err(|e| format!("Invalid JWS: {}", e))?;
+    let jws = match jws.as_object() {
+        Some(jws) => jws,
+        None => return Err("JWS must be an object".to_string()),
+    };
+    let payload = match string_member(jws, "payload")? {
+        Some(payload) => payload,
+        None => return Err("JWS has no payload".to_string()),
+    };
+    let mut signatures = Vec::<&Map<String, Value>>::new();
+    match jws.get("signatures") {
+        Some(Value::Array(entries)) => {
+            for entry in entries.iter() {
+                match entry.as_object() {
+                    Some(signature) => signatures.push(signature),
+                    None => return Err("JWS signatures must be objects".to_string()),
+                }
+            }
+        }
+        Some(_) => return Err("JWS signatures must be an array".to_string()),
+        None => signatures.push(jws),
+    }
+    let algorithm = jws_algorithm(public_key);
+    for signature in signatures.iter() {
+        let protected = match string_member(signature, "protected")? {
+            Some(protected) => protected,
+            None => return Err("JWS signature has no protected header".to_string()),
+        };
+        // Skip signatures made with other keys or with headers that don't decode, but not ones
+        // that claim this key and fail
+        let header: Value = match base64url_decode(protected, "Protected header") {
+            Ok(header) => serde_json::from_slice(&header).unwrap_or(Value::Null),
+            Err(_) => continue,
+        };
+        let key_id = public_key.jwk_key_id();
+        let matches_key = header.get("alg").and_then(|alg| alg.as_str()) == Some(algorithm.as_str())
+            && header.get("kid").and_then(|kid| kid.as_str()).unwrap_or(&key_id) == key_id;
+        if !matches_key {
+            continue;
+        }
+        let value = match string_member(signature, "signature")? {
+            Some(value) => value,
+            None => return Err("JWS signature has no signature value".to_string()),
+        };
+        return verify_jws(public_key, protected, payload, value);
+    }
+    return Err("JWS has no signature for this public key".to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_signature::{HybridKeyBundle, HybridSeed, KeyEncapsulationPublicKey};
+    use crate::test_utils::{increment_bytes, test_seed, test_seed_with_all_schemes};
+
+    #[test]
+    fn test_jwk_round_trip() {
+        let seed = test_seed_with_all_schemes();
+        let signature_public_key = seed.signature_public_key().unwrap();
+        let jwk = signature_public_key.to_jwk();
+        assert_eq!(SignaturePublicKey::from_jwk(&jwk), Ok(signature_public_key.clone()));
+        assert!(KeyEncapsulationPublicKey::from_jwk(&jwk).is_err());
+        let jwk: Value = serde_json::from_str(&jwk).unwrap();
+        assert_eq!(jwk["kty"], "HYB");
+        assert_eq!(jwk["use"], "sig");
+        assert!(jwk["alg"].as_str().unwrap().starts_with(JWS_ALGORITHM_PREFIX));
+
+        let key_encapsulation_public_key = seed.key_encapsulation_public_key().unwrap();
+        let jwk = key_encapsulation_public_key.to_jwk();
+        assert_eq!(KeyEncapsulationPublicKey::from_jwk(&jwk), Ok(key_encapsulation_public_key));
+        assert!(!jwk.contains("\"alg\""));
+
+        let mut wrong_kid: Value = serde_json::from_str(&signature_public_key.to_jwk()).unwrap();
+        wrong_kid["kid"] = Value::String(URL_SAFE_NO_PAD.encode([0u8; 32]));
+        assert!(SignaturePublicKey::from_jwk(&wrong_kid.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_jws_round_trip() {
+        let seed = test_seed_with_all_schemes();
+        let public_key = seed.signature_public_key().unwrap();
+        let claims = br#"{"sub":"api-client","exp":1700000000}"#;
+
+        let token = jws_sign_compact(&seed, claims).unwrap();
+        assert_eq!(token.split('.').count(), 3);
+        assert_eq!(jws_verify_compact(&public_key, &token), Ok(claims.to_vec()));
+        let json = jws_sign_json(&seed, claims).unwrap();
+        assert_eq!(jws_verify_json(&public_key, &json), Ok(claims.to_vec()));
+
+        // The general serialization may carry signatures by other keys
+        let flattened: Value = serde_json::from_str(&json).unwrap();
+        let general = json!({
+            "payload": flattened["payload"],
+            "signatures": [
+                {"protected": URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256"}"#), "signature": "AA"},
+                {"protected": "not base64url!", "signature": "AA"},
+                {"protected": flattened["protected"], "signature": flattened["signature"]},
+            ],
+        });
+        assert_eq!(jws_verify_json(&public_key, &general.to_string()), Ok(claims.to_vec()));
+        let malformed = json!({
+            "payload": flattened["payload"],
+            "signatures": ["AA", {"protected": flattened["protected"], "signature": flattened["signature"]}],
+        });
+        assert!(jws_verify_json(&public_key, &malformed.to_string()).is_err());
+
+        // A bundle holding the same keys produces tokens for the same key
+        let bundle = HybridKeyBundle::from_seed(&seed).unwrap();
+        assert_eq!(jws_verify_compact(&public_key, &jws_sign_compact(&bundle, claims).unwrap()), Ok(claims.to_vec()));
+    }
+
+    #[test]
+    fn test_jws_rejects_tampering() {
+        let seed = test_seed_with_all_schemes();
+        let public_key = seed.signature_public_key().unwrap();
+        let token = jws_sign_compact(&seed, b"payload").unwrap();
+        let parts: Vec<&str> = token.split('.').collect();
+
+        let other_payload = format!("{}.{}.{}", parts[0], URL_SAFE_NO_PAD.encode(b"other"), parts[2]);
+        let none_header = format!("{}.{}.{}", URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#), parts[1], parts[2]);
+        let stripped = format!("{}.{}.", parts[0], parts[1]);
+        let crit = format!("{}.{}.{}", URL_SAFE_NO_PAD.encode(format!(r#"{{"alg":"{}","crit":["exp"]}}"#, jws_algorithm(&public_key))), parts[1], parts[2]);
+        for token in [other_payload, none_header, stripped, crit, format!("{}.{}", parts[0], parts[1])] {
+            assert!(jws_verify_compact(&public_key, &token).is_err(), "{}", token);
+        }
+
+        // Keys without components can neither sign nor verify
+        let empty_seed = HybridSeed::wrap(&[], &[], &test_seed).unwrap();
+        assert!(jws_sign_compact(&empty_seed, b"payload").is_err());
+        let empty_header = URL_SAFE_NO_PAD.encode(format!(r#"{{"alg":"{}"}}"#, jws_algorithm(&empty_seed.signature_public_key().unwrap())));
+        assert!(jws_verify_compact(&empty_seed.signature_public_key().unwrap(), &format!("{}.{}.", empty_header, parts[1])).is_err());
+
+        let mut other_seed = test_seed.to_vec();
+        increment_bytes(&mut other_seed, 1);
+        let signature_names: Vec<String> = signature::get_name_to_ref_mapping().keys().cloned().collect();
+        if !signature_names.is_empty() {
+            let other_public_key = HybridSeed::wrap(&signature_names, &[], &other_seed).unwrap().signature_public_key().unwrap();
+            assert!(jws_verify_compact(&other_public_key, &token).is_err());
+        }
+    }
+}
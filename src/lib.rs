@@ -1,12 +1,34 @@
-mod hybrid_signature;
-mod schemes;
-mod scheme_info_mapping;
-mod tests;
-#[cfg(test)]
-mod test_utils;
-
-pub use crate::hybrid_signature::generate_address;
-pub use crate::hybrid_signature::*;
-pub use crate::schemes::key_encapsulation::KeyEncapsulationScheme;
-pub use crate::schemes::signature::SignatureScheme;
-pub use crate::schemes::AlgorithmPurpose;
\ No newline at end of file
+mod hybrid_signature;
+mod schemes;
+mod scheme_info_mapping;
+mod varint;
+mod secret;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "bech32")]
+pub mod text_encoding;
+#[cfg(feature = "pkcs8")]
+pub mod key_containers;
+pub mod component_keys;
+#[cfg(feature = "jose")]
+pub mod jose;
+#[cfg(feature = "cose")]
+pub mod cose;
+#[cfg(feature = "x509")]
+pub mod x509;
+mod self_test;
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod test_vectors;
+mod tests;
+#[cfg(test)]
+mod test_utils;
+
+pub use crate::hybrid_signature::generate_address;
+pub use crate::hybrid_signature::*;
+pub use crate::schemes::key_encapsulation::KeyEncapsulationScheme;
+pub use crate::schemes::signature::SignatureScheme;
+pub use crate::schemes::signature::check_signature_scheme;
+pub use crate::schemes::{Keypair, SchemeInfo, SizeInfo, SizeKind};
+pub use crate::schemes::AlgorithmPurpose;
+pub use crate::secret::SecretBytes;
+pub use crate::self_test::self_test;
\ No newline at end of file
@@ -0,0 +1,15 @@
+use crate::schemes::{SizeInfo, SizeKind};
+
+// Shared by the signature and key encapsulation conformance checks
+pub(crate) fn assert_size_matches(size_info: &SizeInfo, len: usize, what: &str, name: &str) {
+    match size_info.kind {
+        SizeKind::FixedSized => {
+            let fixed_size = size_info.fixed_size.expect("FixedSized without fixed_size") as usize;
+            assert_eq!(len, fixed_size, "{} length of {} does not match SchemeInfo", what, name);
+        }
+        SizeKind::VariableSized => {
+            let max_size = size_info.max_size.expect("VariableSized without max_size") as usize;
+            assert!(len <= max_size, "{} length of {} exceeds the maximum size in SchemeInfo", what, name);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::schemes::key_encapsulation::{get_name_to_ref_mapping, KeyEncapsulationScheme};
+use crate::schemes::conformance::assert_size_matches;
+use crate::test_utils::{increment_bytes, test_seed};
+use log::info;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use test_log::test;
+
+fn check_key_encapsulation_scheme(name: &str, scheme: &dyn KeyEncapsulationScheme) {
+    let scheme_info = scheme.get_scheme_info();
+
+    // Key generation must be deterministic in the seed
+    let keypair = scheme.generate_keypair(&test_seed);
+    let keypair_again = scheme.generate_keypair(&test_seed);
+    assert_eq!(keypair.pk, keypair_again.pk, "{} keygen is not deterministic", name);
+    assert_eq!(keypair.sk, keypair_again.sk, "{} keygen is not deterministic", name);
+    let mut other_seed = test_seed.clone();
+    increment_bytes(&mut other_seed, 1);
+    let other_keypair = scheme.generate_keypair(&other_seed);
+    assert_ne!(keypair.pk, other_keypair.pk, "{} keygen ignores the seed", name);
+    assert_size_matches(&scheme_info.pk_size_info, keypair.pk.len(), "Public key", name);
+    assert_size_matches(&scheme_info.sk_size_info, keypair.sk.len(), "Secret key", name);
+
+    // Both sides must agree on the shared secret
+    let (ss, ct) = scheme.encapsulate(&keypair.pk);
+    assert_size_matches(&scheme_info.ct_size_info, ct.len(), "Ciphertext", name);
+    let decapsulated_ss = scheme.decapsulate(&ct, &keypair.sk);
+    assert_eq!(ss, decapsulated_ss, "{} encapsulate and decapsulate disagree", name);
+
+    // Encapsulating with an rng is either refused or reproducible with the same rng
+    if let Ok((rng_ss, rng_ct)) = scheme.encapsulate_with_rng(&keypair.pk, &mut StdRng::seed_from_u64(42)) {
+        assert_eq!(scheme.decapsulate(&rng_ct, &keypair.sk), rng_ss, "{} encapsulate_with_rng and decapsulate disagree", name);
+        let encapsulated_again = scheme.encapsulate_with_rng(&keypair.pk, &mut StdRng::seed_from_u64(42));
+        assert_eq!(encapsulated_again, Ok((rng_ss, rng_ct)), "{} does not take its encapsulation randomness from the rng", name);
+    }
+
+    // A tampered ciphertext must decapsulate to something else (implicit rejection)
+    let mut tampered_ct = ct.clone();
+    let middle = tampered_ct.len() / 2;
+    tampered_ct[middle] ^= 1;
+    let tampered_ss = scheme.decapsulate(&tampered_ct, &keypair.sk);
+    assert_eq!(tampered_ss.len(), ss.len(), "{} rejection secret has a different length", name);
+    assert_ne!(tampered_ss, ss, "{} accepted a tampered ciphertext", name);
+
+    // The wrong secret key must not recover the shared secret either
+    let wrong_ss = scheme.decapsulate(&ct, &other_keypair.sk);
+    assert_ne!(wrong_ss, ss, "{} decapsulated with the wrong secret key", name);
+}
+
+#[test]
+#[cfg_attr(not(feature = "saber"), ignore = "no key encapsulation scheme is compiled in")]
+fn test_all_key_encapsulation_schemes() {
+    let mapping = get_name_to_ref_mapping();
+    for (name, scheme_ref) in mapping.iter() {
+        info!("Testing {}...", name);
+        check_key_encapsulation_scheme(name, scheme_ref.scheme_impl.as_ref());
+    }
+}
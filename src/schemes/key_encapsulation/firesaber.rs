@@ -1,86 +1,140 @@
-use crate::schemes::key_encapsulation::KeyEncapsulationScheme;
-use crate::schemes::scheme_info::*;
-use crate::schemes::Keypair;
-use saber::firesaber::{keygen_seed, keygen, encapsulate, decapsulate, PublicKey, SecretKey, Ciphertext};
-
-pub struct Firesaber;
-
-impl KeyEncapsulationScheme for Firesaber {
-    fn get_scheme_info(&self) -> SchemeInfo {
-        // Lengths from: https://openquantumsafe.org/liboqs/algorithms/kem/saber
-        return SchemeInfo {
-            pk_size_info: SizeInfo {
-                kind: SizeKind::FixedSized,
-                fixed_size: Some(1312),
-                variable_size_bytelen: None,
-            },
-            ct_size_info: SizeInfo {
-                kind: SizeKind::FixedSized,
-                fixed_size: Some(1472),
-                variable_size_bytelen: None,
-            },
-        };
-    }
-    fn generate_keypair(&self, seed: &[u8]) -> Keypair {
-        let sk = keygen_seed(seed);
-        let pk = sk.public_key();
-        let pk_bytes: Vec<u8> = pk.to_bytes().into_bytes().to_vec();
-        let sk_bytes: Vec<u8> = sk.to_bytes().into_bytes().to_vec();
-        return Keypair {
-            pk: pk_bytes,
-            sk: sk_bytes,
-        };
-    }
-
-    fn encapsulate(&self, pk_other_bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
-        let pk_other = match PublicKey::from_bytes(pk_other_bytes) {
-            Ok(pk) => pk,
-            Err(err) => panic!("Error decoding public key: {}", err),
-        };
-        let (ss, ct) = encapsulate(&pk_other);
-        return (ss.as_bytes().to_vec(), ct.as_bytes().to_vec());
-    }
-
-    fn decapsulate(&self, ct_bytes: &[u8], sk_bytes: &[u8]) -> std::vec::Vec<u8> {
-        let secret_key = match SecretKey::from_bytes(sk_bytes) {
-            Ok(sk) => sk,
-            Err(err) => panic!("Error decoding secret key: {}", err),
-        };
-        let ciphertext = match Ciphertext::from_bytes(ct_bytes) {
-            Ok(ct) => ct,
-            Err(err) => panic!("Error decoding ciphertext: {}", err),
-        };
-        
-        let server_secret = decapsulate(&ciphertext, &secret_key);
-        return ciphertext.as_bytes().to_vec();
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use hex_literal::hex;
-    use crate::test_utils::{increment_bytes, test_seed};
-    
-    fn test_seed_full() -> Vec<u8> {
-        let mut seed_config = hex!("010000").to_vec();
-        let mut test_seed_real = test_seed.to_vec();
-        seed_config.append(&mut test_seed_real);
-        return seed_config;
-    }
-
-    #[test]
-    fn test_cycle() {
-        // Consider a server with a key pair
-        let server_secret_key = keygen();
-        let server_public_key = server_secret_key.public_key();
-
-        // Let a client encapsulate some shared secret for the server
-        let (client_secret, ciphertext) = encapsulate(&server_public_key);
-
-        // Have the server decrypt the ciphertext
-        let server_secret = decapsulate(&ciphertext, &server_secret_key);
-
-        assert_eq!(client_secret.as_slice(), server_secret.as_slice());
-    }
+use crate::schemes::key_encapsulation::KeyEncapsulationScheme;
+use crate::schemes::scheme_info::*;
+use crate::schemes::Keypair;
+use saber::firesaber::{keygen_seed, keygen, encapsulate, decapsulate, PublicKey, SecretKey, Ciphertext};
+use rand_core::CryptoRngCore;
+use hex_literal::hex;
+
+pub struct Firesaber;
+
+// Self test inputs. The saber crate draws encapsulation randomness itself, so only key generation
+// and decapsulation are deterministic. Their answers (pk and sk for KAT_SEED, ss for a recorded
+// ct) still have to be recorded against the pinned backend revision (d0bd165 in Cargo.lock);
+// until then the self test checks that they are reproducible and agree with each other.
+const KAT_SEED: [u8; 48] = hex!("fe17131c10c31ebdd26493c4b77553d1e14a826276e627a018fff1c79a7fe4ccb2184ed6b8e2fed27007aa77b4f725c4");
+
+impl KeyEncapsulationScheme for Firesaber {
+    #[allow(deprecated)]
+    fn get_scheme_info(&self) -> SchemeInfo {
+        // Lengths from: https://openquantumsafe.org/liboqs/algorithms/kem/saber
+        return SchemeInfo {
+            pk_size_info: SizeInfo {
+                kind: SizeKind::FixedSized,
+                fixed_size: Some(1312),
+                max_size: None,
+                variable_size_bytelen: None,
+            },
+            ct_size_info: SizeInfo {
+                kind: SizeKind::FixedSized,
+                fixed_size: Some(1472),
+                max_size: None,
+                variable_size_bytelen: None,
+            },
+            sk_size_info: SizeInfo {
+                kind: SizeKind::FixedSized,
+                fixed_size: Some(3040),
+                max_size: None,
+                variable_size_bytelen: None,
+            },
+        };
+    }
+    fn generate_keypair(&self, seed: &[u8]) -> Keypair {
+        let sk = keygen_seed(seed);
+        let pk = sk.public_key();
+        let pk_bytes: Vec<u8> = pk.to_bytes().into_bytes().to_vec();
+        let sk_bytes: Vec<u8> = sk.to_bytes().into_bytes().to_vec();
+        return Keypair {
+            pk: pk_bytes,
+            sk: sk_bytes,
+        };
+    }
+
+    fn encapsulate(&self, pk_other_bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let pk_other = match PublicKey::from_bytes(pk_other_bytes) {
+            Ok(pk) => pk,
+            Err(err) => panic!("Error decoding public key: {}", err),
+        };
+        let (ss, ct) = encapsulate(&pk_other);
+        return (ss.as_bytes().to_vec(), ct.as_bytes().to_vec());
+    }
+
+    fn encapsulate_with_rng(&self, _pk_other_bytes: &[u8], _rng: &mut dyn CryptoRngCore) -> Result<(Vec<u8>, Vec<u8>), String> {
+        // The saber crate draws the encapsulation coins itself and has no seeded variant, so
+        // pretending to use the rng would hand out ciphertexts the caller believes are reproducible
+        return Err("Firesaber can't encapsulate with a supplied rng".to_string());
+    }
+
+    fn decapsulate(&self, ct_bytes: &[u8], sk_bytes: &[u8]) -> std::vec::Vec<u8> {
+        let secret_key = match SecretKey::from_bytes(sk_bytes) {
+            Ok(sk) => sk,
+            Err(err) => panic!("Error decoding secret key: {}", err),
+        };
+        let ciphertext = match Ciphertext::from_bytes(ct_bytes) {
+            Ok(ct) => ct,
+            Err(err) => panic!("Error decoding ciphertext: {}", err),
+        };
+        
+        let shared_secret = decapsulate(&ciphertext, &secret_key);
+        return shared_secret.as_bytes().to_vec();
+    }
+
+    fn known_answer_test(&self) -> Result<(), String> {
+        let keypair = self.generate_keypair(&KAT_SEED);
+        let keypair_again = self.generate_keypair(&KAT_SEED);
+        if keypair.pk != keypair_again.pk || keypair.sk != keypair_again.sk {
+            return Err("Firesaber key generation is not deterministic".to_string());
+        }
+        let (ss, ct) = self.encapsulate(&keypair.pk);
+        if self.decapsulate(&ct, &keypair.sk) != ss {
+            return Err("Firesaber decapsulation does not recover the shared secret".to_string());
+        }
+        // Implicit rejection has to be deterministic and must not return the real shared secret
+        let mut tampered_ct = ct.clone();
+        tampered_ct[0] ^= 1;
+        let rejected_ss = self.decapsulate(&tampered_ct, &keypair.sk);
+        if rejected_ss == ss || rejected_ss != self.decapsulate(&tampered_ct, &keypair.sk) {
+            return Err("Firesaber implicit rejection misbehaves".to_string());
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+    use crate::test_utils::{increment_bytes, test_seed};
+    
+    fn test_seed_full() -> Vec<u8> {
+        let mut seed_config = hex!("010000").to_vec();
+        let mut test_seed_real = test_seed.to_vec();
+        seed_config.append(&mut test_seed_real);
+        return seed_config;
+    }
+
+    #[test]
+    fn test_cycle() {
+        // Consider a server with a key pair
+        let server_secret_key = keygen();
+        let server_public_key = server_secret_key.public_key();
+
+        // Let a client encapsulate some shared secret for the server
+        let (client_secret, ciphertext) = encapsulate(&server_public_key);
+
+        // Have the server decrypt the ciphertext
+        let server_secret = decapsulate(&ciphertext, &server_secret_key);
+
+        assert_eq!(client_secret.as_slice(), server_secret.as_slice());
+    }
+
+    #[test]
+    fn test_known_answer() {
+        assert_eq!(Firesaber.known_answer_test(), Ok(()));
+    }
+
+    #[test]
+    fn test_encapsulate_with_rng_is_refused() {
+        let keypair = Firesaber.generate_keypair(&test_seed);
+        assert!(Firesaber.encapsulate_with_rng(&keypair.pk, &mut rand_core::OsRng).is_err());
+    }
 }
\ No newline at end of file
@@ -1,9 +1,23 @@
-use crate::schemes::keypair::Keypair;
-use crate::schemes::SchemeInfo;
-
-pub trait KeyEncapsulationScheme {
-    fn get_scheme_info(&self) -> SchemeInfo;
-    fn generate_keypair(&self, seed: &[u8]) -> Keypair;
-    fn encapsulate(&self, pk_other_bytes: &[u8]) -> (Vec<u8>, Vec<u8>);
-    fn decapsulate(&self, ct_bytes: &[u8], sk_bytes: &[u8]) -> std::vec::Vec<u8>;
-}
+use crate::schemes::keypair::Keypair;
+use crate::schemes::SchemeInfo;
+use rand_core::CryptoRngCore;
+
+pub trait KeyEncapsulationScheme {
+    fn get_scheme_info(&self) -> SchemeInfo;
+    fn generate_keypair(&self, seed: &[u8]) -> Keypair;
+    fn encapsulate(&self, pk_other_bytes: &[u8]) -> (Vec<u8>, Vec<u8>);
+    // Draws the encapsulation randomness from rng, backends that can't take it from outside return an error
+    fn encapsulate_with_rng(&self, _pk_other_bytes: &[u8], _rng: &mut dyn CryptoRngCore) -> Result<(Vec<u8>, Vec<u8>), String> {
+        return Err("Scheme can't encapsulate with a supplied rng".to_string());
+    }
+    fn decapsulate(&self, ct_bytes: &[u8], sk_bytes: &[u8]) -> std::vec::Vec<u8>;
+    // Checks the backend against vectors it ships with, used by self_test(). Schemes without
+    // vectors are only covered by the pairwise consistency tests.
+    fn known_answer_test(&self) -> Result<(), String> {
+        return Ok(());
+    }
+    // Curve of the OKP JSON Web Key (RFC 8037), if the scheme has one
+    fn jwk_curve(&self) -> Option<&'static str> {
+        return None;
+    }
+}
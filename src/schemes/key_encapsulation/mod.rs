@@ -1,11 +1,13 @@
-#[cfg(feature = "saber")]
-mod firesaber;
-mod key_encapsulation_scheme;
-mod mapping;
-mod scheme_info_reference;
-
-#[cfg(feature = "saber")]
-pub use firesaber::Firesaber;
-pub use key_encapsulation_scheme::KeyEncapsulationScheme;
-pub use mapping::{get_id_to_ref_mapping, get_name_to_ref_mapping};
-pub use scheme_info_reference::SchemeInfoReference;
+#[cfg(test)]
+mod conformance;
+#[cfg(feature = "saber")]
+mod firesaber;
+mod key_encapsulation_scheme;
+mod mapping;
+mod scheme_info_reference;
+
+#[cfg(feature = "saber")]
+pub use firesaber::Firesaber;
+pub use key_encapsulation_scheme::KeyEncapsulationScheme;
+pub use mapping::{get_id_to_ref_mapping, get_name_to_ref_mapping};
+pub use scheme_info_reference::SchemeInfoReference;
@@ -1,4 +1,19 @@
-pub struct Keypair {
-    pub pk: Vec<u8>,
-    pub sk: Vec<u8>
+use std::fmt;
+use zeroize::Zeroize;
+
+pub struct Keypair {
+    pub pk: Vec<u8>,
+    pub sk: Vec<u8>
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.debug_struct("Keypair").field("pk", &self.pk).field("sk", &"<redacted>").finish();
+    }
+}
+
+impl Drop for Keypair {
+    fn drop(&mut self) {
+        self.sk.zeroize();
+    }
 }
\ No newline at end of file
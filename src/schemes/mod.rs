@@ -1,7 +1,8 @@
-pub mod key_encapsulation;
-mod keypair;
-mod scheme_info;
-pub mod signature;
-
-pub use keypair::Keypair;
-pub use scheme_info::*;
+mod conformance;
+pub mod key_encapsulation;
+mod keypair;
+mod scheme_info;
+pub mod signature;
+
+pub use keypair::Keypair;
+pub use scheme_info::*;
@@ -1,20 +1,35 @@
-pub enum AlgorithmPurpose {
-    Signature,
-    KeyEncapsulation,
-}
-
-pub enum SizeKind {
-    FixedSized,
-    VariableSized,
-}
-
-pub struct SizeInfo {
-    pub kind: SizeKind,
-    pub fixed_size: Option<u32>,
-    pub variable_size_bytelen: Option<u8>,
-}
-
-pub struct SchemeInfo {
-    pub ct_size_info: SizeInfo,
-    pub pk_size_info: SizeInfo,
-}
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AlgorithmPurpose {
+    Signature,
+    KeyEncapsulation,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SizeKind {
+    FixedSized,
+    VariableSized,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SizeInfo {
+    pub kind: SizeKind,
+    pub fixed_size: Option<u32>,
+    // Upper bound for variable sized values, enforced when encoding and decoding
+    pub max_size: Option<u32>,
+    // Length of the little endian length prefix of variable sized values in the legacy encoding.
+    // Only needed to read and write legacy keys, signatures and ciphertexts.
+    #[deprecated(note = "variable sized values are prefixed with a varint, except in the legacy encoding")]
+    pub variable_size_bytelen: Option<u8>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SchemeInfo {
+    pub ct_size_info: SizeInfo,
+    pub pk_size_info: SizeInfo,
+    // Secret keys supplied from outside a seed are checked against it before use
+    pub sk_size_info: SizeInfo,
+}
@@ -0,0 +1,101 @@
+use crate::schemes::signature::SignatureScheme;
+use crate::schemes::conformance::assert_size_matches;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+// Seed branches are BLAKE3 outputs, so the harness uses seeds of the same length
+const CONFORMANCE_SEED: [u8; 32] = [0x5a; 32];
+const CONFORMANCE_OTHER_SEED: [u8; 32] = [0xa5; 32];
+const CONFORMANCE_MESSAGE: &[u8] = b"Hybrid signature conformance message";
+
+fn assert_rejected(scheme: &dyn SignatureScheme, message: &[u8], signature: &[u8], pk: &[u8], what: &str, name: &str) {
+    let result = catch_unwind(AssertUnwindSafe(|| scheme.verify_message(message, signature, pk)));
+    match result {
+        Ok(verified) => assert!(!verified, "{} accepted {}", name, what),
+        Err(_) => panic!("{} panicked on {}", name, what),
+    }
+}
+
+// Checks that a signature scheme behaves the way the hybrid layer expects it to.
+// Scheme authors can call this from their own tests; it panics on the first violation.
+pub fn check_signature_scheme(name: &str, scheme: &dyn SignatureScheme) {
+    let scheme_info = scheme.get_scheme_info();
+
+    // Key generation must be deterministic in the seed
+    let keypair = scheme.generate_keypair(&CONFORMANCE_SEED);
+    let keypair_again = scheme.generate_keypair(&CONFORMANCE_SEED);
+    assert_eq!(keypair.pk, keypair_again.pk, "{} keygen is not deterministic", name);
+    assert_eq!(keypair.sk, keypair_again.sk, "{} keygen is not deterministic", name);
+    let other_keypair = scheme.generate_keypair(&CONFORMANCE_OTHER_SEED);
+    assert_ne!(keypair.pk, other_keypair.pk, "{} keygen ignores the seed", name);
+    assert_ne!(keypair.sk, other_keypair.sk, "{} keygen ignores the seed", name);
+
+    // Sizes must be what SchemeInfo promises, or the combined encodings break
+    assert_size_matches(&scheme_info.pk_size_info, keypair.pk.len(), "Public key", name);
+    assert_size_matches(&scheme_info.sk_size_info, keypair.sk.len(), "Secret key", name);
+    let signature = scheme.sign_message(&keypair.sk, CONFORMANCE_MESSAGE).expect("Signing with a generated key must succeed");
+    assert_size_matches(&scheme_info.ct_size_info, signature.len(), "Signature", name);
+    let empty_signature = scheme.sign_message(&keypair.sk, &[]).expect("Signing with a generated key must succeed");
+    assert_size_matches(&scheme_info.ct_size_info, empty_signature.len(), "Signature", name);
+
+    // Round trip
+    assert!(scheme.verify_message(CONFORMANCE_MESSAGE, &signature, &keypair.pk), "{} rejected a valid signature", name);
+    assert!(scheme.verify_message(&[], &empty_signature, &keypair.pk), "{} rejected a valid signature over an empty message", name);
+
+    // Signing with an rng is either refused or reproducible with the same rng
+    if let Ok(rng_signature) = scheme.sign_message_with_rng(&keypair.sk, CONFORMANCE_MESSAGE, &mut StdRng::from_seed(CONFORMANCE_SEED)) {
+        assert!(scheme.verify_message(CONFORMANCE_MESSAGE, &rng_signature, &keypair.pk), "{} rejected a signature made with an rng", name);
+        let rng_signature_again = scheme.sign_message_with_rng(&keypair.sk, CONFORMANCE_MESSAGE, &mut StdRng::from_seed(CONFORMANCE_SEED));
+        assert_eq!(rng_signature_again, Ok(rng_signature), "{} does not take its signing randomness from the rng", name);
+    }
+
+    // Mutated messages, signatures and keys
+    let mut mutated_message = CONFORMANCE_MESSAGE.to_vec();
+    mutated_message[0] ^= 1;
+    assert_rejected(scheme, &mutated_message, &signature, &keypair.pk, "a mutated message", name);
+    assert_rejected(scheme, &[], &signature, &keypair.pk, "an empty message", name);
+    for position in [0, signature.len() / 2, signature.len() - 1] {
+        let mut mutated_signature = signature.clone();
+        mutated_signature[position] ^= 1;
+        assert_rejected(scheme, CONFORMANCE_MESSAGE, &mutated_signature, &keypair.pk, "a mutated signature", name);
+    }
+    for position in [0, keypair.pk.len() / 2, keypair.pk.len() - 1] {
+        let mut mutated_pk = keypair.pk.clone();
+        mutated_pk[position] ^= 1;
+        assert_rejected(scheme, CONFORMANCE_MESSAGE, &signature, &mutated_pk, "a mutated public key", name);
+    }
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &signature, &other_keypair.pk, "another public key", name);
+
+    // Malformed inputs must be rejected without panicking
+    let mut extended_signature = signature.clone();
+    extended_signature.push(0);
+    let mut extended_pk = keypair.pk.clone();
+    extended_pk.push(0);
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &[], &keypair.pk, "an empty signature", name);
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &signature[..signature.len() - 1], &keypair.pk, "a truncated signature", name);
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &extended_signature, &keypair.pk, "an extended signature", name);
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &vec![0u8; signature.len()], &keypair.pk, "an all-zero signature", name);
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &vec![0xffu8; signature.len()], &keypair.pk, "an all-ones signature", name);
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &signature, &[], "an empty public key", name);
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &signature, &keypair.pk[..keypair.pk.len() - 1], "a truncated public key", name);
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &signature, &extended_pk, "an extended public key", name);
+    assert_rejected(scheme, CONFORMANCE_MESSAGE, &signature, &vec![0u8; keypair.pk.len()], "an all-zero public key", name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemes::signature::get_name_to_ref_mapping;
+    use log::info;
+    use test_log::test;
+
+    #[test]
+    fn test_all_signature_schemes() {
+        let mapping = get_name_to_ref_mapping();
+        for (name, scheme_ref) in mapping.iter() {
+            info!("Testing {}...", name);
+            check_signature_scheme(name, scheme_ref.scheme_impl.as_ref());
+        }
+    }
+}
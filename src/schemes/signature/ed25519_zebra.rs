@@ -1,86 +1,143 @@
-use ed25519_zebra::{SigningKey, VerificationKey};
-use hex_literal::hex;
-use crate::schemes::{Keypair, SchemeInfo, SizeInfo, SizeKind};
-use crate::SignatureScheme;
-
-pub struct Ed25519Zebra;
-
-impl SignatureScheme for Ed25519Zebra {
-    fn get_scheme_info(&self) -> SchemeInfo {
-        return SchemeInfo {
-            ct_size_info: SizeInfo {
-                kind: SizeKind::FixedSized,
-                fixed_size: Some(64),
-                variable_size_bytelen: None,
-            },
-            pk_size_info: SizeInfo {
-                kind: SizeKind::FixedSized,
-                variable_size_bytelen: None,
-                fixed_size: Some(32),
-            },
-        };
-    }
-
-    fn generate_keypair(&self, seed: &[u8]) -> Keypair {
-        let mut hasher = blake3::Hasher::new();
-        // echo -n "Salt for hashing Ed2219Zebra seeds into static-length seeds as the keygenerator requires it\!" | openssl sha384
-        hasher.update(&hex!("a340634e35773aa1692f46914284363e13fc7f3d13ef302da3f008582807556ad72091d1d4b18ce80c7c0ad1e99c78ee"));
-        hasher.update(seed);
-        let mut seed = [0u8; 32];
-        let mut output_reader = hasher.finalize_xof();
-        output_reader.fill(&mut seed);
-
-        let sk = SigningKey::from(seed);
-        let vk_bytes: [u8; 32] = VerificationKey::from(&sk).into();
-        let sk_bytes: [u8; 32] = sk.into();
-
-        return Keypair {
-            pk: vk_bytes.to_vec(),
-            sk: sk_bytes.to_vec(),
-        };
-    }
-
-    fn sign_message(&self, sk: &[u8], message: &[u8]) -> Vec<u8> {
-        let sk = SigningKey::try_from(sk).unwrap();
-        let sig = sk.sign(message);
-        let sig_bytes: [u8; 64] = sig.into();
-        return sig_bytes.to_vec();
-    }
-
-    fn verify_message(&self, message: &[u8], signature: &[u8], pk: &[u8]) -> bool {
-        let signature: [u8; 64] = signature.try_into().unwrap();
-        VerificationKey::try_from(pk)
-            .and_then(|vk| vk.verify(&signature.into(), message))
-            .is_ok()
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use log::debug;
-    use test_log::test;
-    use super::*;
-    use crate::test_utils::{increment_bytes, test_seed};
-
-    #[test]
-    fn seed_change_test() {
-        let kp = Ed25519Zebra.generate_keypair(&test_seed);
-        let mut incremented_seed = test_seed.clone();
-        increment_bytes(&mut incremented_seed, 1);
-        debug!("test_seed: {}\nincremented_seed: {}", hex::encode(&test_seed), hex::encode(&incremented_seed));
-        let kp2 = Ed25519Zebra.generate_keypair(&incremented_seed);
-        assert_ne!(kp.pk, kp2.pk);
-        assert_ne!(kp.sk, kp2.sk);
-    }
-
-    #[test]
-    fn test_sign_verify() {
-        let kp = Ed25519Zebra.generate_keypair(&test_seed);
-        assert_eq!(kp.pk, hex!("4e0da33007ac2fbc7e29f9f23de059d510b5a6a1764628f4aede79c555da67ee"));
-        assert_eq!(kp.sk, hex!("38b7765cf4dcbe89c61e61bb2b9c72dcc6ab8168f123790b58eaab2a068acf58"));
-        let message = "Test from Peter".as_bytes();
-        let signature = Ed25519Zebra.sign_message(&kp.sk, message);
-        debug!("Message: {} signature: {}", hex::encode(&message), hex::encode(&signature));
-        assert_eq!(signature, hex!("6e5b395b0cb4a5326d08634a70bdfecb0ff1a373a5a448f5c7afe31652854daf462eca1b730b7cfdb8c23a23eb000d193dcca10a93a0fd17ce3f80fef48da20f"));
-    }
+use ed25519_zebra::{SigningKey, VerificationKey};
+use hex_literal::hex;
+use crate::schemes::{Keypair, SchemeInfo, SizeInfo, SizeKind};
+use crate::SignatureScheme;
+use rand_core::CryptoRngCore;
+
+pub struct Ed25519Zebra;
+
+// Known-answer vectors for self tests, the same values as test_sign_verify below
+const KAT_SEED: [u8; 48] = hex!("fe17131c10c31ebdd26493c4b77553d1e14a826276e627a018fff1c79a7fe4ccb2184ed6b8e2fed27007aa77b4f725c4");
+const KAT_PK: [u8; 32] = hex!("4e0da33007ac2fbc7e29f9f23de059d510b5a6a1764628f4aede79c555da67ee");
+const KAT_SK: [u8; 32] = hex!("38b7765cf4dcbe89c61e61bb2b9c72dcc6ab8168f123790b58eaab2a068acf58");
+const KAT_MESSAGE: &[u8] = b"Test from Peter";
+const KAT_SIGNATURE: [u8; 64] = hex!("6e5b395b0cb4a5326d08634a70bdfecb0ff1a373a5a448f5c7afe31652854daf462eca1b730b7cfdb8c23a23eb000d193dcca10a93a0fd17ce3f80fef48da20f");
+
+impl SignatureScheme for Ed25519Zebra {
+    #[allow(deprecated)]
+    fn get_scheme_info(&self) -> SchemeInfo {
+        return SchemeInfo {
+            ct_size_info: SizeInfo {
+                kind: SizeKind::FixedSized,
+                fixed_size: Some(64),
+                max_size: None,
+                variable_size_bytelen: None,
+            },
+            pk_size_info: SizeInfo {
+                kind: SizeKind::FixedSized,
+                max_size: None,
+                fixed_size: Some(32),
+                variable_size_bytelen: None,
+            },
+            sk_size_info: SizeInfo {
+                kind: SizeKind::FixedSized,
+                fixed_size: Some(32),
+                max_size: None,
+                variable_size_bytelen: None,
+            },
+        };
+    }
+
+    fn generate_keypair(&self, seed: &[u8]) -> Keypair {
+        let mut hasher = blake3::Hasher::new();
+        // echo -n "Salt for hashing Ed2219Zebra seeds into static-length seeds as the keygenerator requires it\!" | openssl sha384
+        hasher.update(&hex!("a340634e35773aa1692f46914284363e13fc7f3d13ef302da3f008582807556ad72091d1d4b18ce80c7c0ad1e99c78ee"));
+        hasher.update(seed);
+        let mut seed = [0u8; 32];
+        let mut output_reader = hasher.finalize_xof();
+        output_reader.fill(&mut seed);
+
+        let sk = SigningKey::from(seed);
+        let vk_bytes: [u8; 32] = VerificationKey::from(&sk).into();
+        let sk_bytes: [u8; 32] = sk.into();
+
+        return Keypair {
+            pk: vk_bytes.to_vec(),
+            sk: sk_bytes.to_vec(),
+        };
+    }
+
+    fn sign_message(&self, sk: &[u8], message: &[u8]) -> Result<Vec<u8>, String> {
+        let sk = match SigningKey::try_from(sk) {
+            Ok(sk) => sk,
+            Err(e) => return Err(format!("Ed25519Zebra secret key can't be loaded: {}", e)),
+        };
+        let sig = sk.sign(message);
+        let sig_bytes: [u8; 64] = sig.into();
+        return Ok(sig_bytes.to_vec());
+    }
+
+    // Ed25519 signatures are deterministic, there is no randomness to draw
+    fn sign_message_with_rng(&self, sk: &[u8], message: &[u8], _rng: &mut dyn CryptoRngCore) -> Result<Vec<u8>, String> {
+        return self.sign_message(sk, message);
+    }
+
+    fn verify_message(&self, message: &[u8], signature: &[u8], pk: &[u8]) -> bool {
+        let signature: [u8; 64] = match signature.try_into() {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        VerificationKey::try_from(pk)
+            .and_then(|vk| vk.verify(&signature.into(), message))
+            .is_ok()
+    }
+
+    fn known_answer_test(&self) -> Result<(), String> {
+        let kp = self.generate_keypair(&KAT_SEED);
+        if kp.pk != KAT_PK || kp.sk != KAT_SK {
+            return Err("Ed25519Zebra key generation does not match the known answer".to_string());
+        }
+        let signature = self.sign_message(&kp.sk, KAT_MESSAGE)?;
+        if signature != KAT_SIGNATURE {
+            return Err("Ed25519Zebra signature does not match the known answer".to_string());
+        }
+        if !self.verify_message(KAT_MESSAGE, &KAT_SIGNATURE, &KAT_PK) {
+            return Err("Ed25519Zebra rejected the known answer signature".to_string());
+        }
+        return Ok(());
+    }
+
+    // Public keys are the 32-byte RFC 8032 encoding and secret keys the 32-byte RFC 8032 seed
+    fn openssh_key_type(&self) -> Option<&'static str> {
+        return Some("ssh-ed25519");
+    }
+
+    fn jwk_curve(&self) -> Option<&'static str> {
+        return Some("Ed25519");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::debug;
+    use test_log::test;
+    use super::*;
+    use crate::test_utils::{increment_bytes, test_seed};
+
+    #[test]
+    fn seed_change_test() {
+        let kp = Ed25519Zebra.generate_keypair(&test_seed);
+        let mut incremented_seed = test_seed.clone();
+        increment_bytes(&mut incremented_seed, 1);
+        debug!("test_seed: {}\nincremented_seed: {}", hex::encode(&test_seed), hex::encode(&incremented_seed));
+        let kp2 = Ed25519Zebra.generate_keypair(&incremented_seed);
+        assert_ne!(kp.pk, kp2.pk);
+        assert_ne!(kp.sk, kp2.sk);
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let kp = Ed25519Zebra.generate_keypair(&test_seed);
+        assert_eq!(kp.pk, hex!("4e0da33007ac2fbc7e29f9f23de059d510b5a6a1764628f4aede79c555da67ee"));
+        assert_eq!(kp.sk, hex!("38b7765cf4dcbe89c61e61bb2b9c72dcc6ab8168f123790b58eaab2a068acf58"));
+        let message = "Test from Peter".as_bytes();
+        let signature = Ed25519Zebra.sign_message(&kp.sk, message).unwrap();
+        debug!("Message: {} signature: {}", hex::encode(&message), hex::encode(&signature));
+        assert_eq!(signature, hex!("6e5b395b0cb4a5326d08634a70bdfecb0ff1a373a5a448f5c7afe31652854daf462eca1b730b7cfdb8c23a23eb000d193dcca10a93a0fd17ce3f80fef48da20f"));
+    }
+
+    #[test]
+    fn test_known_answer() {
+        assert_eq!(Ed25519Zebra.known_answer_test(), Ok(()));
+    }
 }
\ No newline at end of file
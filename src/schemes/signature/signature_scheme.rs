@@ -1,9 +1,29 @@
-use crate::schemes::keypair::Keypair;
-use crate::schemes::SchemeInfo;
-
-pub trait SignatureScheme {
-    fn get_scheme_info(&self) -> SchemeInfo;
-    fn generate_keypair(&self, seed: &[u8]) -> Keypair;
-    fn sign_message(&self, sk: &[u8], message: &[u8]) -> Vec<u8>;
-    fn verify_message(&self, message: &[u8], signature: &[u8], pk: &[u8]) -> bool;
+use crate::schemes::keypair::Keypair;
+use crate::schemes::SchemeInfo;
+use rand_core::CryptoRngCore;
+
+pub trait SignatureScheme {
+    fn get_scheme_info(&self) -> SchemeInfo;
+    fn generate_keypair(&self, seed: &[u8]) -> Keypair;
+    // Fails on secret keys the backend can't load
+    fn sign_message(&self, sk: &[u8], message: &[u8]) -> Result<Vec<u8>, String>;
+    // Draws any signing randomness from rng, randomized backends that can't take it from outside
+    // return an error. Deterministic schemes sign as sign_message does.
+    fn sign_message_with_rng(&self, _sk: &[u8], _message: &[u8], _rng: &mut dyn CryptoRngCore) -> Result<Vec<u8>, String> {
+        return Err("Scheme can't sign with a supplied rng".to_string());
+    }
+    fn verify_message(&self, message: &[u8], signature: &[u8], pk: &[u8]) -> bool;
+    // Checks the backend against vectors it ships with, used by self_test(). Schemes without
+    // vectors are only covered by the pairwise consistency tests.
+    fn known_answer_test(&self) -> Result<(), String> {
+        return Ok(());
+    }
+    // Key type in OpenSSH public key lines, if OpenSSH supports the scheme
+    fn openssh_key_type(&self) -> Option<&'static str> {
+        return None;
+    }
+    // Curve of the OKP JSON Web Key (RFC 8037), if the scheme has one
+    fn jwk_curve(&self) -> Option<&'static str> {
+        return None;
+    }
 }
\ No newline at end of file
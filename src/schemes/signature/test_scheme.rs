@@ -0,0 +1,126 @@
+use crate::schemes::{Keypair, SchemeInfo, SizeInfo, SizeKind};
+use crate::SignatureScheme;
+use hex_literal::hex;
+use rand_core::CryptoRngCore;
+
+// Insecure stand-in for a signature scheme, so the hybrid layer can be tested without any
+// backend compiled in. The public key is the secret key and signatures are keyed BLAKE3 hashes
+// of the message. The variable sized configuration makes signatures of 48 to 63 bytes.
+pub struct TestScheme {
+    pub variable_sized: bool,
+}
+
+const SIGNATURE_LEN: usize = 32;
+const MAX_VARIABLE_SIGNATURE_LEN: usize = 63;
+
+// Known-answer vectors for self tests
+const KAT_SEED: [u8; 48] = hex!("fe17131c10c31ebdd26493c4b77553d1e14a826276e627a018fff1c79a7fe4ccb2184ed6b8e2fed27007aa77b4f725c4");
+const KAT_MESSAGE: &[u8] = b"Test scheme";
+const KAT_PK: [u8; 32] = hex!("1021650ecfa02041f7461f00faf7dc598ae5b23892d5e1f974725df611085799");
+// Signature of the fixed sized configuration
+const KAT_SIGNATURE: [u8; 32] = hex!("a8fd39201d6c49280aeb3bcaa9a08c6f5600a1847d657cce0191c8bbb304d198");
+
+impl TestScheme {
+    fn signature(&self, key: &[u8; 32], message: &[u8]) -> Vec<u8> {
+        let mut output = blake3::Hasher::new_keyed(key).update(message).finalize_xof();
+        if !self.variable_sized {
+            let mut signature = vec![0u8; SIGNATURE_LEN];
+            output.fill(&mut signature);
+            return signature;
+        }
+        let mut len = [0u8; 1];
+        output.fill(&mut len);
+        let mut signature = vec![0u8; 48 + (len[0] % 16) as usize];
+        output.fill(&mut signature);
+        return signature;
+    }
+}
+
+impl SignatureScheme for TestScheme {
+    #[allow(deprecated)]
+    fn get_scheme_info(&self) -> SchemeInfo {
+        let ct_size_info = match self.variable_sized {
+            true => SizeInfo {
+                kind: SizeKind::VariableSized,
+                fixed_size: None,
+                max_size: Some(MAX_VARIABLE_SIGNATURE_LEN as u32),
+                variable_size_bytelen: Some(2),
+            },
+            false => SizeInfo {
+                kind: SizeKind::FixedSized,
+                fixed_size: Some(SIGNATURE_LEN as u32),
+                max_size: None,
+                variable_size_bytelen: None,
+            },
+        };
+        return SchemeInfo {
+            ct_size_info,
+            pk_size_info: SizeInfo {
+                kind: SizeKind::FixedSized,
+                fixed_size: Some(32),
+                max_size: None,
+                variable_size_bytelen: None,
+            },
+            sk_size_info: SizeInfo {
+                kind: SizeKind::FixedSized,
+                fixed_size: Some(32),
+                max_size: None,
+                variable_size_bytelen: None,
+            },
+        };
+    }
+
+    fn generate_keypair(&self, seed: &[u8]) -> Keypair {
+        let key = blake3::derive_key("hybrid_cryptography test scheme key", seed);
+        return Keypair {
+            pk: key.to_vec(),
+            sk: key.to_vec(),
+        };
+    }
+
+    fn sign_message(&self, sk: &[u8], message: &[u8]) -> Result<Vec<u8>, String> {
+        let key: [u8; 32] = match sk.try_into() {
+            Ok(key) => key,
+            Err(_) => return Err("Test scheme secret keys are 32 bytes".to_string()),
+        };
+        return Ok(self.signature(&key, message));
+    }
+
+    fn sign_message_with_rng(&self, sk: &[u8], message: &[u8], _rng: &mut dyn CryptoRngCore) -> Result<Vec<u8>, String> {
+        return self.sign_message(sk, message);
+    }
+
+    fn verify_message(&self, message: &[u8], signature: &[u8], pk: &[u8]) -> bool {
+        let key: [u8; 32] = match pk.try_into() {
+            Ok(key) => key,
+            Err(_) => return false,
+        };
+        return self.signature(&key, message) == signature;
+    }
+
+    fn known_answer_test(&self) -> Result<(), String> {
+        let kp = self.generate_keypair(&KAT_SEED);
+        if kp.pk != KAT_PK {
+            return Err("Test scheme key generation does not match the known answer".to_string());
+        }
+        let signature = self.sign_message(&kp.sk, KAT_MESSAGE)?;
+        if !self.variable_sized && signature != KAT_SIGNATURE {
+            return Err("Test scheme signature does not match the known answer".to_string());
+        }
+        if !self.verify_message(KAT_MESSAGE, &signature, &kp.pk) {
+            return Err("Test scheme rejected its own signature".to_string());
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_answer() {
+        assert_eq!(TestScheme { variable_sized: false }.known_answer_test(), Ok(()));
+        assert_eq!(TestScheme { variable_sized: true }.known_answer_test(), Ok(()));
+    }
+}
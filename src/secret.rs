@@ -0,0 +1,70 @@
+use std::fmt;
+use std::ops::Deref;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+// Bytes that must not outlive their use: zeroized on drop, never printed and compared in
+// constant time. Only the length is treated as public.
+#[derive(Clone)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn as_bytes(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        return SecretBytes(bytes);
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl AsRef<[u8]> for SecretBytes {
+    fn as_ref(&self) -> &[u8] {
+        return &self.0;
+    }
+}
+
+impl PartialEq for SecretBytes {
+    fn eq(&self, other: &Self) -> bool {
+        return self.0.as_slice().ct_eq(other.0.as_slice()).into();
+    }
+}
+
+impl Eq for SecretBytes {}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "SecretBytes(<{} bytes redacted>)", self.0.len());
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_bytes() {
+        let secret = SecretBytes::from(vec![0xab; 32]);
+        assert_eq!(format!("{:?}", secret), "SecretBytes(<32 bytes redacted>)");
+        assert_eq!(secret, SecretBytes::from(vec![0xab; 32]));
+        assert_ne!(secret, SecretBytes::from(vec![0xab; 31]));
+        assert_ne!(secret, SecretBytes::from(vec![0xac; 32]));
+        assert_eq!(&secret[..], &[0xab; 32][..]);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::hybrid_signature::{combine_shared_secrets, create_scheme_seed_branch, decapsulate, encapsulate, generate_combined_public_key, hash_combined_public_key, KeyGenerationOptions, sign_message, verify_message, wrap_seed};
+use crate::schemes::key_encapsulation::{self, KeyEncapsulationScheme};
+use crate::schemes::signature::{self, SignatureScheme};
+use crate::schemes::{AlgorithmPurpose, Keypair};
+use hex_literal::hex;
+
+const SELF_TEST_SEED: [u8; 48] = hex!("fe17131c10c31ebdd26493c4b77553d1e14a826276e627a018fff1c79a7fe4ccb2184ed6b8e2fed27007aa77b4f725c4");
+const PAIRWISE_CONSISTENCY_MESSAGE: &[u8] = b"Pairwise consistency test";
+const CHECKED: KeyGenerationOptions = KeyGenerationOptions {
+    pairwise_consistency_test: true,
+};
+
+// Known answers for the hybrid combiners, which only depend on BLAKE3
+const KAT_SEED_BRANCH: [u8; 32] = hex!("64c5947b45e5d4d2351d753dec81397fadfa2ea30e1750b03e4d138bc50b411e");
+const KAT_ADDRESS: [u8; 32] = hex!("4225ce1cc2b2eb6ffbfcaf09f33ee8c7752c70358f873f44f901543a5118b2a6");
+const KAT_COMBINED_SHARED_SECRET: [u8; 32] = hex!("1438af47d472517d6b23b8c1c43774a1f5a4056b01b63b54f8e5129949dd0cc2");
+
+pub(crate) fn signature_pairwise_consistency_test(scheme: &dyn SignatureScheme, keypair: &Keypair) -> Result<(), String> {
+    let signature = scheme.sign_message(&keypair.sk, PAIRWISE_CONSISTENCY_MESSAGE)?;
+    if !scheme.verify_message(PAIRWISE_CONSISTENCY_MESSAGE, &signature, &keypair.pk) {
+        return Err("Pairwise consistency test failed: signature did not verify".to_string());
+    }
+    let mut modified_message = PAIRWISE_CONSISTENCY_MESSAGE.to_vec();
+    modified_message[0] ^= 1;
+    if scheme.verify_message(&modified_message, &signature, &keypair.pk) {
+        return Err("Pairwise consistency test failed: signature verified for a modified message".to_string());
+    }
+    return Ok(());
+}
+
+pub(crate) fn key_encapsulation_pairwise_consistency_test(scheme: &dyn KeyEncapsulationScheme, keypair: &Keypair) -> Result<(), String> {
+    let (ss, ct) = scheme.encapsulate(&keypair.pk);
+    if scheme.decapsulate(&ct, &keypair.sk) != ss {
+        return Err("Pairwise consistency test failed: shared secrets do not match".to_string());
+    }
+    return Ok(());
+}
+
+fn combiner_known_answer_test() -> Result<(), String> {
+    let seed_branch = create_scheme_seed_branch(&SELF_TEST_SEED, AlgorithmPurpose::Signature, 1, 0);
+    if seed_branch.as_bytes() != KAT_SEED_BRANCH {
+        return Err("Seed branch does not match the known answer".to_string());
+    }
+    if hash_combined_public_key(&SELF_TEST_SEED)? != KAT_ADDRESS {
+        return Err("Combined public key hash does not match the known answer".to_string());
+    }
+    if combine_shared_secrets(&[SELF_TEST_SEED[..32].to_vec().into(), SELF_TEST_SEED[32..].to_vec().into()]).as_bytes() != KAT_COMBINED_SHARED_SECRET {
+        return Err("Shared secret combiner does not match the known answer".to_string());
+    }
+    return Ok(());
+}
+
+// Power-on self test: known-answer tests for the combiners and every compiled-in scheme that
+// ships vectors, followed by pairwise consistency tests of each scheme on its own and hybridized.
+pub fn self_test() -> Result<(), String> {
+    combiner_known_answer_test()?;
+
+    let signature_mapping = signature::get_name_to_ref_mapping();
+    let mut signature_algorithms = Vec::<String>::new();
+    for (name, scheme_ref) in signature_mapping.iter() {
+        let scheme_impl = scheme_ref.scheme_impl.as_ref();
+        let error_context = |e: String| format!("Self test of {} failed: {}", name, e);
+        scheme_impl.known_answer_test().map_err(error_context)?;
+        let keypair = scheme_impl.generate_keypair(&SELF_TEST_SEED);
+        signature_pairwise_consistency_test(scheme_impl, &keypair).map_err(error_context)?;
+        signature_algorithms.push(name.to_string());
+    }
+
+    let key_encapsulation_mapping = key_encapsulation::get_name_to_ref_mapping();
+    let mut key_encapsulation_algorithms = Vec::<String>::new();
+    for (name, scheme_ref) in key_encapsulation_mapping.iter() {
+        let scheme_impl = scheme_ref.scheme_impl.as_ref();
+        let error_context = |e: String| format!("Self test of {} failed: {}", name, e);
+        scheme_impl.known_answer_test().map_err(error_context)?;
+        let keypair = scheme_impl.generate_keypair(&SELF_TEST_SEED);
+        key_encapsulation_pairwise_consistency_test(scheme_impl, &keypair).map_err(error_context)?;
+        key_encapsulation_algorithms.push(name.to_string());
+    }
+
+    // Exercise the hybrid layer with every scheme at once
+    let seed = wrap_seed(&signature_algorithms, &key_encapsulation_algorithms, SELF_TEST_SEED)?;
+    let signature_public_key = generate_combined_public_key(AlgorithmPurpose::Signature, &seed, CHECKED)?;
+    let signature = sign_message(&seed, PAIRWISE_CONSISTENCY_MESSAGE)?;
+    if !verify_message(PAIRWISE_CONSISTENCY_MESSAGE, &signature_public_key, &signature) {
+        return Err("Self test of hybrid signatures failed: signature did not verify".to_string());
+    }
+    let key_encapsulation_public_key = generate_combined_public_key(AlgorithmPurpose::KeyEncapsulation, &seed, CHECKED)?;
+    let (ss, ct) = encapsulate(&key_encapsulation_public_key)?;
+    if decapsulate(&seed, &ct)? != ss {
+        return Err("Self test of hybrid key encapsulation failed: shared secrets do not match".to_string());
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemes::{SchemeInfo, SizeInfo, SizeKind};
+    use rand_core::CryptoRngCore;
+    use test_log::test;
+
+    #[test]
+    fn test_self_test() {
+        assert_eq!(self_test(), Ok(()));
+    }
+
+    struct MisbehavingScheme;
+
+    impl SignatureScheme for MisbehavingScheme {
+        #[allow(deprecated)]
+        fn get_scheme_info(&self) -> SchemeInfo {
+            return SchemeInfo {
+                ct_size_info: SizeInfo {
+                    kind: SizeKind::FixedSized,
+                    fixed_size: Some(64),
+                    max_size: None,
+                    variable_size_bytelen: None,
+                },
+                pk_size_info: SizeInfo {
+                    kind: SizeKind::FixedSized,
+                    fixed_size: Some(SELF_TEST_SEED.len() as u32),
+                    max_size: None,
+                    variable_size_bytelen: None,
+                },
+                sk_size_info: SizeInfo {
+                    kind: SizeKind::FixedSized,
+                    fixed_size: Some(SELF_TEST_SEED.len() as u32),
+                    max_size: None,
+                    variable_size_bytelen: None,
+                },
+            };
+        }
+        fn generate_keypair(&self, seed: &[u8]) -> Keypair {
+            return Keypair { pk: seed.to_vec(), sk: seed.to_vec() };
+        }
+        fn sign_message(&self, _sk: &[u8], _message: &[u8]) -> Result<Vec<u8>, String> {
+            return Ok(vec![0u8; 64]);
+        }
+        fn sign_message_with_rng(&self, sk: &[u8], message: &[u8], _rng: &mut dyn CryptoRngCore) -> Result<Vec<u8>, String> {
+            return self.sign_message(sk, message);
+        }
+        fn verify_message(&self, _message: &[u8], _signature: &[u8], _pk: &[u8]) -> bool {
+            return true;
+        }
+        // Known answer tests only cover fixed vectors, the pairwise test is what catches this
+        fn known_answer_test(&self) -> Result<(), String> {
+            return Ok(());
+        }
+    }
+
+    #[test]
+    fn test_pairwise_consistency_fails_closed() {
+        let keypair = MisbehavingScheme.generate_keypair(&SELF_TEST_SEED);
+        assert!(signature_pairwise_consistency_test(&MisbehavingScheme, &keypair).is_err());
+    }
+
+    #[test]
+    fn test_checked_combined_public_key() {
+        let signature_mapping = signature::get_name_to_ref_mapping();
+        let names: Vec<String> = signature_mapping.keys().cloned().collect();
+        let seed = wrap_seed(&names, &[], SELF_TEST_SEED).unwrap();
+        let checked = generate_combined_public_key(AlgorithmPurpose::Signature, &seed, CHECKED);
+        assert_eq!(checked, generate_combined_public_key(AlgorithmPurpose::Signature, &seed, KeyGenerationOptions::default()));
+        assert!(checked.is_ok());
+    }
+}
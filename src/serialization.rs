@@ -0,0 +1,231 @@
+use crate::hybrid_signature::{parse_combined_public_key, Address, CombinedPublicKey, HybridCiphertext, HybridSeed, HybridSignature, KeyPurpose, ParsedCombinedPublicKey};
+use crate::hybrid_signature::ComponentEncoding;
+use crate::schemes::AlgorithmPurpose;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+// Byte strings are hex in human-readable formats like JSON and raw bytes in binary formats like CBOR
+fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        return serializer.serialize_str(&hex::encode(bytes));
+    }
+    return serializer.serialize_bytes(bytes);
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        return formatter.write_str("a hex string or a byte string");
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<u8>, E> {
+        return hex::decode(value).map_err(E::custom);
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Vec<u8>, E> {
+        return Ok(value.to_vec());
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Vec<u8>, E> {
+        return Ok(value);
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut bytes = Vec::new();
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        return Ok(bytes);
+    }
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    if deserializer.is_human_readable() {
+        return deserializer.deserialize_str(BytesVisitor);
+    }
+    return deserializer.deserialize_byte_buf(BytesVisitor);
+}
+
+// Types that wrap a validated byte string go through their parsing constructor when deserialized
+macro_rules! impl_serde_for_bytes {
+    ($type:ty) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                return serialize_bytes(self.as_bytes(), serializer);
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let bytes = deserialize_bytes(deserializer)?;
+                return <$type>::from_bytes(&bytes).map_err(de::Error::custom);
+            }
+        }
+    };
+}
+
+impl_serde_for_bytes!(HybridSeed);
+impl_serde_for_bytes!(HybridSignature);
+impl_serde_for_bytes!(HybridCiphertext);
+
+impl<P: KeyPurpose> Serialize for CombinedPublicKey<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serialize_bytes(self.as_bytes(), serializer);
+    }
+}
+
+impl<'de, P: KeyPurpose> Deserialize<'de> for CombinedPublicKey<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        return CombinedPublicKey::from_bytes(&bytes).map_err(de::Error::custom);
+    }
+}
+
+impl<P: KeyPurpose> Serialize for Address<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serialize_bytes(self.as_bytes(), serializer);
+    }
+}
+
+impl<'de, P: KeyPurpose> Deserialize<'de> for Address<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        return Address::from_bytes(&bytes).map_err(de::Error::custom);
+    }
+}
+
+// A parsed combined public key is written out as its purpose and a list of components
+#[derive(Serialize, Deserialize)]
+struct SerializedComponent {
+    scheme_id: u16,
+    scheme_config_id: u16,
+    #[serde(serialize_with = "serialize_bytes", deserialize_with = "deserialize_bytes")]
+    public_key: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedParsedCombinedPublicKey {
+    purpose: AlgorithmPurpose,
+    components: Vec<SerializedComponent>,
+    encoding: ComponentEncoding,
+}
+
+impl Serialize for ParsedCombinedPublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let serialized = SerializedParsedCombinedPublicKey {
+            purpose: self.purpose,
+            components: self
+                .components
+                .iter()
+                .map(|(scheme_id, pk)| SerializedComponent {
+                    scheme_id: scheme_id.0,
+                    scheme_config_id: scheme_id.1,
+                    public_key: pk.clone(),
+                })
+                .collect(),
+            encoding: self.encoding,
+        };
+        return serialized.serialize(serializer);
+    }
+}
+
+impl<'de> Deserialize<'de> for ParsedCombinedPublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedParsedCombinedPublicKey::deserialize(deserializer)?;
+        let components = serialized
+            .components
+            .into_iter()
+            .map(|component| ((component.scheme_id, component.scheme_config_id), component.public_key))
+            .collect();
+        let parsed = ParsedCombinedPublicKey::new(serialized.purpose, serialized.encoding, components);
+        // Same checks as for the encoded form: known schemes, sizes that fit and no duplicates
+        let encoded = parsed.encode().map_err(de::Error::custom)?;
+        parse_combined_public_key(parsed.purpose, &encoded).map_err(de::Error::custom)?;
+        return Ok(parsed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hybrid_signature::*;
+    use crate::schemes::signature::get_name_to_ref_mapping;
+    use crate::schemes::{AlgorithmPurpose, SchemeInfo};
+    use crate::test_utils::{test_seed, test_seed_with_all_schemes};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use std::fmt::Debug;
+
+    fn to_cbor<T: Serialize>(value: &T) -> Vec<u8> {
+        let mut cbor = Vec::new();
+        ciborium::ser::into_writer(value, &mut cbor).unwrap();
+        return cbor;
+    }
+
+    fn assert_round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T, bytes: &[u8]) {
+        let json = serde_json::to_string(value).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex::encode(bytes)));
+        assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value);
+        let cbor = to_cbor(value);
+        // A CBOR byte string is its length header followed by the raw bytes
+        assert!(cbor.ends_with(bytes));
+        assert_eq!(&ciborium::de::from_reader::<T, _>(&cbor[..]).unwrap(), value);
+    }
+
+    fn cbor_bytes(bytes: &[u8]) -> ciborium::value::Value {
+        return ciborium::value::Value::Bytes(bytes.to_vec());
+    }
+
+    #[test]
+    fn test_byte_types_round_trip() {
+        let seed = test_seed_with_all_schemes();
+        assert_round_trip(&seed, seed.as_bytes());
+        let signature_public_key = seed.signature_public_key().unwrap();
+        assert_round_trip(&signature_public_key, signature_public_key.as_bytes());
+        assert_round_trip(&signature_public_key.address(), signature_public_key.address().as_bytes());
+        let signature = seed.sign(b"CAFEBABE").unwrap();
+        assert_round_trip(&signature, signature.as_bytes());
+        let (_, ciphertext) = seed.key_encapsulation_public_key().unwrap().encapsulate().unwrap();
+        assert_round_trip(&ciphertext, ciphertext.as_bytes());
+    }
+
+    #[test]
+    fn test_invalid_bytes_are_rejected() {
+        assert!(serde_json::from_str::<HybridSeed>(&format!("\"{}\"", hex::encode(test_seed))).is_err());
+        assert!(serde_json::from_str::<HybridSeed>("\"not hex\"").is_err());
+        assert!(serde_json::from_str::<SignatureAddress>("\"00\"").is_err());
+        assert!(ciborium::de::from_reader::<SignaturePublicKey, _>(&to_cbor(&cbor_bytes(&[0xff, 0xff]))[..]).is_err());
+    }
+
+    #[test]
+    fn test_parsed_combined_public_key_round_trip() {
+        let seed = test_seed_with_all_schemes();
+        let parsed = seed.key_encapsulation_public_key().unwrap().parse();
+        let json = serde_json::to_string(&parsed).unwrap();
+        let from_json: ParsedCombinedPublicKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json.encode(), parsed.encode());
+        assert_eq!(from_json.purpose, AlgorithmPurpose::KeyEncapsulation);
+        let from_cbor: ParsedCombinedPublicKey = ciborium::de::from_reader(&to_cbor(&parsed)[..]).unwrap();
+        assert_eq!(from_cbor.encode(), parsed.encode());
+
+        let unknown_scheme = r#"{"purpose":"Signature","components":[{"scheme_id":999,"scheme_config_id":0,"public_key":"00"}]}"#;
+        assert!(serde_json::from_str::<ParsedCombinedPublicKey>(unknown_scheme).is_err());
+    }
+
+    #[test]
+    fn test_scheme_info_round_trip() {
+        let mapping = get_name_to_ref_mapping();
+        for scheme_ref in mapping.values() {
+            let scheme_info = scheme_ref.scheme_impl.get_scheme_info();
+            let json = serde_json::to_string(&scheme_info).unwrap();
+            let from_json: SchemeInfo = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&from_json).unwrap(), json);
+            let from_cbor: SchemeInfo = ciborium::de::from_reader(&to_cbor(&scheme_info)[..]).unwrap();
+            assert_eq!(serde_json::to_string(&from_cbor).unwrap(), json);
+        }
+        assert_eq!(serde_json::to_string(&AlgorithmPurpose::Signature).unwrap(), "\"Signature\"");
+    }
+}
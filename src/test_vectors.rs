@@ -0,0 +1,257 @@
+use crate::hybrid_signature::{decapsulate, encapsulate, encapsulate_with_rng, generate_combined_public_key, hash_combined_public_key, parse_seed, KeyGenerationOptions, sign_message, verify_message, wrap_seed};
+use crate::hybrid_signature::{check_entropy_len, MAX_SEED_ENTROPY_LEN, MIN_SEED_ENTROPY_LEN, SEED_VERSION};
+use crate::schemes::{key_encapsulation, signature, AlgorithmPurpose};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+const VECTOR_ENTROPY: [u8; 48] = hex_literal::hex!("fe17131c10c31ebdd26493c4b77553d1e14a826276e627a018fff1c79a7fe4ccb2184ed6b8e2fed27007aa77b4f725c4");
+const VECTOR_MESSAGE: &[u8] = b"Hybrid cryptography test vector";
+
+// Byte strings are hex encoded so other implementations can consume the vectors directly
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SeedBranchVector {
+    pub purpose: String,
+    pub scheme_id: u16,
+    pub scheme_config_id: u16,
+    pub seed_branch: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TestVector {
+    pub signature_algorithms: Vec<String>,
+    pub key_encapsulation_algorithms: Vec<String>,
+    pub entropy: String,
+    pub seed: String,
+    pub seed_branches: Vec<SeedBranchVector>,
+    pub signature_public_key: String,
+    pub signature_address: String,
+    pub key_encapsulation_public_key: String,
+    pub key_encapsulation_address: String,
+    pub message: String,
+    pub signature: String,
+    pub ciphertext: String,
+    pub shared_secret: String,
+}
+
+// Encapsulation randomness is derived from the entropy so vectors are reproducible
+fn vector_rng(entropy: &[u8]) -> StdRng {
+    return StdRng::from_seed(*blake3::hash(entropy).as_bytes());
+}
+
+fn purpose_name(purpose: &AlgorithmPurpose) -> &'static str {
+    return match purpose {
+        AlgorithmPurpose::Signature => "signature",
+        AlgorithmPurpose::KeyEncapsulation => "key_encapsulation",
+    };
+}
+
+fn decode_field(name: &str, value: &str) -> Result<Vec<u8>, String> {
+    return hex::decode(value).map_err(|e| format!("Field {} is not valid hex: {}", name, e));
+}
+
+fn check_field(name: &str, expected: &str, actual: &[u8]) -> Result<(), String> {
+    if decode_field(name, expected)? != actual {
+        return Err(format!("Field {} does not match: expected {}, got {}", name, expected, hex::encode(actual)));
+    }
+    return Ok(());
+}
+
+pub fn generate_test_vector(
+    signature_algorithms: &[String],
+    key_encapsulation_algorithms: &[String],
+    entropy: &[u8],
+    message: &[u8],
+) -> TestVector {
+    let seed = wrap_seed(signature_algorithms, key_encapsulation_algorithms, entropy).expect("Test vector algorithms or entropy are invalid");
+    let parsed_seed = parse_seed(&seed).expect("Wrapped seed could not be parsed");
+    let mut seed_branches = Vec::<SeedBranchVector>::new();
+    for (purpose, scheme_ids) in [
+        (AlgorithmPurpose::Signature, &parsed_seed.signature_scheme_ids),
+        (AlgorithmPurpose::KeyEncapsulation, &parsed_seed.key_encapsulation_scheme_ids),
+    ] {
+        for scheme_id in scheme_ids.iter() {
+            let purpose_name = purpose_name(&purpose).to_string();
+            let seed_branch = parsed_seed.scheme_seed_branch(purpose, *scheme_id).expect("Wrapped seed could not be used");
+            seed_branches.push(SeedBranchVector {
+                purpose: purpose_name,
+                scheme_id: scheme_id.0,
+                scheme_config_id: scheme_id.1,
+                seed_branch: hex::encode(seed_branch),
+            });
+        }
+    }
+    let signature_public_key = generate_combined_public_key(AlgorithmPurpose::Signature, &seed, KeyGenerationOptions::default()).expect("Wrapped seed could not be used");
+    let key_encapsulation_public_key = generate_combined_public_key(AlgorithmPurpose::KeyEncapsulation, &seed, KeyGenerationOptions::default()).expect("Wrapped seed could not be used");
+    let signature = sign_message(&seed, message).expect("Wrapped seed could not be used");
+    // Backends that can't take their randomness from the rng make vectors that only replay through decapsulation
+    let (shared_secret, ciphertext) = match encapsulate_with_rng(&key_encapsulation_public_key, &mut vector_rng(entropy)) {
+        Ok(encapsulated) => encapsulated,
+        Err(_) => encapsulate(&key_encapsulation_public_key).expect("Generated public key could not be used"),
+    };
+    return TestVector {
+        signature_algorithms: signature_algorithms.to_vec(),
+        key_encapsulation_algorithms: key_encapsulation_algorithms.to_vec(),
+        entropy: hex::encode(entropy),
+        seed: hex::encode(&seed),
+        seed_branches,
+        signature_address: hex::encode(hash_combined_public_key(&signature_public_key).expect("Generated public key could not be hashed")),
+        signature_public_key: hex::encode(&signature_public_key),
+        key_encapsulation_address: hex::encode(hash_combined_public_key(&key_encapsulation_public_key).expect("Generated public key could not be hashed")),
+        key_encapsulation_public_key: hex::encode(&key_encapsulation_public_key),
+        message: hex::encode(message),
+        signature: hex::encode(&signature),
+        ciphertext: hex::encode(&ciphertext),
+        shared_secret: hex::encode(&shared_secret),
+    };
+}
+
+// Vectors for every compiled-in scheme on its own and for all of them combined
+pub fn generate_default_test_vectors() -> Vec<TestVector> {
+    let entropy = &VECTOR_ENTROPY;
+    let mut signature_algorithms: Vec<String> = signature::get_name_to_ref_mapping().into_keys().collect();
+    let mut key_encapsulation_algorithms: Vec<String> = key_encapsulation::get_name_to_ref_mapping().into_keys().collect();
+    signature_algorithms.sort();
+    key_encapsulation_algorithms.sort();
+    let mut vectors = Vec::<TestVector>::new();
+    vectors.push(generate_test_vector(&[], &[], entropy, VECTOR_MESSAGE));
+    for name in signature_algorithms.iter() {
+        vectors.push(generate_test_vector(&[name.to_string()], &[], entropy, VECTOR_MESSAGE));
+    }
+    for name in key_encapsulation_algorithms.iter() {
+        vectors.push(generate_test_vector(&[], &[name.to_string()], entropy, VECTOR_MESSAGE));
+    }
+    if signature_algorithms.len() + key_encapsulation_algorithms.len() > 1 {
+        vectors.push(generate_test_vector(&signature_algorithms, &key_encapsulation_algorithms, entropy, VECTOR_MESSAGE));
+    }
+    // Shortest and longest supported entropy
+    let short_entropy = &VECTOR_ENTROPY[..MIN_SEED_ENTROPY_LEN];
+    vectors.push(generate_test_vector(&signature_algorithms, &key_encapsulation_algorithms, short_entropy, VECTOR_MESSAGE));
+    let long_entropy = [VECTOR_ENTROPY.to_vec(), VECTOR_ENTROPY[..MAX_SEED_ENTROPY_LEN - VECTOR_ENTROPY.len()].to_vec()].concat();
+    vectors.push(generate_test_vector(&signature_algorithms, &key_encapsulation_algorithms, &long_entropy, VECTOR_MESSAGE));
+    return vectors;
+}
+
+// Returns false when the vector uses schemes that are not compiled in
+pub fn is_test_vector_supported(vector: &TestVector) -> bool {
+    let signature_mapping = signature::get_name_to_ref_mapping();
+    let key_encapsulation_mapping = key_encapsulation::get_name_to_ref_mapping();
+    return vector.signature_algorithms.iter().all(|name| signature_mapping.contains_key(name))
+        && vector.key_encapsulation_algorithms.iter().all(|name| key_encapsulation_mapping.contains_key(name));
+}
+
+// Returns the fields that were not checked because the backends are randomized; a vector is only
+// fully replayed when that list is empty
+pub fn check_test_vector(vector: &TestVector) -> Result<Vec<String>, String> {
+    let mut skipped_fields = Vec::<String>::new();
+    let entropy = decode_field("entropy", &vector.entropy)?;
+    check_entropy_len(entropy.len())?;
+    let message = decode_field("message", &vector.message)?;
+
+    // Vectors of earlier seed versions are replayed from their own seed, which wrap_seed no longer
+    // makes, so only check that it holds the vector's schemes and entropy
+    let seed = decode_field("seed", &vector.seed)?;
+    let parsed_seed = parse_seed(&seed)?;
+    let wrapped_seed = wrap_seed(&vector.signature_algorithms, &vector.key_encapsulation_algorithms, &entropy)?;
+    if parsed_seed.version == SEED_VERSION {
+        check_field("seed", &vector.seed, &wrapped_seed)?;
+    } else {
+        let parsed_wrapped_seed = parse_seed(&wrapped_seed)?;
+        if parsed_seed.seed != parsed_wrapped_seed.seed
+            || parsed_seed.signature_scheme_ids != parsed_wrapped_seed.signature_scheme_ids
+            || parsed_seed.key_encapsulation_scheme_ids != parsed_wrapped_seed.key_encapsulation_scheme_ids
+        {
+            return Err("Field seed does not hold the schemes and entropy of the vector".to_string());
+        }
+    }
+    for branch in vector.seed_branches.iter() {
+        let purpose = match branch.purpose.as_str() {
+            "signature" => AlgorithmPurpose::Signature,
+            "key_encapsulation" => AlgorithmPurpose::KeyEncapsulation,
+            other => return Err(format!("Unknown purpose {}", other)),
+        };
+        let seed_branch = parsed_seed.scheme_seed_branch(purpose, (branch.scheme_id, branch.scheme_config_id))?;
+        check_field("seed_branch", &branch.seed_branch, &seed_branch)?;
+    }
+
+    let signature_public_key = generate_combined_public_key(AlgorithmPurpose::Signature, &seed, KeyGenerationOptions::default())?;
+    check_field("signature_public_key", &vector.signature_public_key, &signature_public_key)?;
+    check_field("signature_address", &vector.signature_address, &hash_combined_public_key(&signature_public_key)?)?;
+    let key_encapsulation_public_key = generate_combined_public_key(AlgorithmPurpose::KeyEncapsulation, &seed, KeyGenerationOptions::default())?;
+    check_field("key_encapsulation_public_key", &vector.key_encapsulation_public_key, &key_encapsulation_public_key)?;
+    check_field("key_encapsulation_address", &vector.key_encapsulation_address, &hash_combined_public_key(&key_encapsulation_public_key)?)?;
+
+    // Randomized signature backends can't reproduce the vector, but must still accept it
+    let vector_signature = decode_field("signature", &vector.signature)?;
+    if !verify_message(&message, &signature_public_key, &vector_signature) {
+        return Err("Field signature does not verify".to_string());
+    }
+    let signature = sign_message(&seed, &message)?;
+    if signature == sign_message(&seed, &message)? {
+        check_field("signature", &vector.signature, &signature)?;
+    } else {
+        skipped_fields.push("signature".to_string());
+    }
+
+    let ciphertext = decode_field("ciphertext", &vector.ciphertext)?;
+    check_field("shared_secret", &vector.shared_secret, decapsulate(&seed, &ciphertext)?.as_bytes())?;
+    // Likewise, only backends that take their randomness from the rng reproduce the ciphertext
+    match encapsulate_with_rng(&key_encapsulation_public_key, &mut vector_rng(&entropy)) {
+        Ok(encapsulated) => {
+            check_field("ciphertext", &vector.ciphertext, &encapsulated.1)?;
+            check_field("shared_secret", &vector.shared_secret, encapsulated.0.as_bytes())?;
+        }
+        Err(_) => skipped_fields.push("ciphertext".to_string()),
+    }
+    return Ok(skipped_fields);
+}
+
+pub fn test_vectors_to_json(vectors: &[TestVector]) -> String {
+    return serde_json::to_string_pretty(vectors).expect("Test vectors could not be serialized");
+}
+
+pub fn test_vectors_from_json(json: &str) -> Result<Vec<TestVector>, String> {
+    return serde_json::from_str(json).map_err(|e| format!("Test vectors could not be parsed: {}", e));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::info;
+    use test_log::test;
+
+    // Vectors of earlier formats keep their own file and are never regenerated
+    const VECTOR_FILES: [(&str, &str); 4] = [
+        ("hybrid_legacy.json", include_str!("../vectors/hybrid_legacy.json")),
+        ("hybrid_v1.json", include_str!("../vectors/hybrid_v1.json")),
+        ("hybrid_v2.json", include_str!("../vectors/hybrid_v2.json")),
+        ("hybrid.json", include_str!("../vectors/hybrid.json")),
+    ];
+
+    #[test]
+    fn test_replay_committed_vectors() {
+        for (file_name, json) in VECTOR_FILES.iter() {
+            let vectors = test_vectors_from_json(json).unwrap();
+            assert!(!vectors.is_empty(), "{}", file_name);
+            for vector in vectors.iter() {
+                if !is_test_vector_supported(vector) {
+                    info!("Skipping vector of {} for {:?} {:?}", file_name, vector.signature_algorithms, vector.key_encapsulation_algorithms);
+                    continue;
+                }
+                assert_eq!(check_test_vector(vector), Ok(Vec::new()), "{}", file_name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_vector_round_trip() {
+        let vector = generate_test_vector(&[], &[], &[7u8; 48], b"CAFEBABE");
+        let json = test_vectors_to_json(&[vector.clone()]);
+        assert_eq!(test_vectors_from_json(&json).unwrap(), vec![vector.clone()]);
+        assert_eq!(check_test_vector(&vector), Ok(Vec::new()));
+
+        let mut tampered = vector.clone();
+        tampered.signature_address = hex::encode([0u8; 32]);
+        assert!(check_test_vector(&tampered).is_err());
+    }
+}
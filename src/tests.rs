@@ -1,71 +1,816 @@
-#[cfg(test)]
-mod tests {
-    use hex_literal::hex;
-    use crate::*;
-    use log::{debug, info};
-    use test_log::test;
-    use crate::AlgorithmPurpose::Signature;
-    use crate::schemes::signature::get_name_to_ref_mapping;
-    use crate::test_utils::{increment_bytes, test_seed};
-
-    #[test]
-    #[cfg(feature = "falcon")]
-    fn test_seed_generator() {
-        let seed1 = generate_random_seed(&["falcon512".to_string()], &[]);
-        let seed2 = generate_random_seed(&["falcon512".to_string()], &[]);
-        assert_ne!(&seed1, &seed2);
-        assert_eq!(&seed1[1..3], &[0, 0]);
-        assert_eq!(&seed2[1..3], &[0, 0]);
-    }
-
-    #[test]
-    fn test_all_signature_algorithms() {
-        let mapping = get_name_to_ref_mapping();
-        let test_msg = hex!("CAFEBABE");
-        for (name, scheme_ref) in mapping.iter() {
-            info!("Testing {}...", name);
-            let seed = wrap_seed(&[name.to_string()], &[], test_seed);
-            let signature = sign_message(&seed, &test_msg);
-            debug!("Signature: {}", hex::encode(&signature));
-            let combined_public_key = generate_combined_public_key(Signature, &seed);
-            let is_valid = verify_message(&test_msg, &combined_public_key, &signature);
-            assert_eq!(is_valid, true);
-            let mut test_msg_increased = test_msg.clone();
-            increment_bytes(&mut test_msg_increased, 1);
-            let is_valid = verify_message(&test_msg_increased, &combined_public_key, &signature);
-            assert_eq!(is_valid, false);
-        }
-    }
-
-    #[test]
-    fn test_fake_combined_public_key() {
-        let mapping = get_name_to_ref_mapping();
-        let test_msg = hex!("CAFEBABE");
-        println!("mapping: {:?}", mapping.len());
-        for (name, scheme_ref) in mapping.iter() {
-            info!("Testing {}...", name);
-            let seed = wrap_seed(&[name.to_string()], &[], test_seed);
-            let signature = sign_message(&seed, &test_msg);
-            let real_combined_public_key = generate_combined_public_key(Signature, &seed);
-            debug!("real_combined_public_key: {}", hex::encode(&real_combined_public_key));
-            debug!("Signature: {}", hex::encode(&signature));
-            // Try a bunch of fake combined public keys
-            for i in 0..100 {
-                let mut combined_public_key = [0u8; 48];
-                getrandom::getrandom(&mut combined_public_key).expect("Random seed could not be loaded!");
-                let is_valid = verify_message(&test_msg, &combined_public_key, &signature);
-                assert_eq!(is_valid, false);
-            }
-            // Try a bunch of fake combined public keys but real algorithm selection
-            for i in 0..100 {
-                let mut combined_public_key = [0u8; 6];
-                getrandom::getrandom(&mut combined_public_key).expect("Random seed could not be loaded!");
-                let start = hex!("0100");
-                let fake_key = vec![start.to_vec(), combined_public_key.to_vec()].concat();
-                debug!("fake_key: {}", hex::encode(&fake_key));
-                let is_valid = verify_message(&test_msg, &fake_key, &signature);
-                assert_eq!(is_valid, false);
-            }
-        }
-    }
-}
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+    use crate::*;
+    use log::{debug, info};
+    use test_log::test;
+    use crate::AlgorithmPurpose::{KeyEncapsulation, Signature};
+    use crate::schemes::key_encapsulation;
+    use crate::schemes::signature::get_name_to_ref_mapping;
+    use crate::test_utils::{increment_bytes, test_seed};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    #[cfg(feature = "falcon")]
+    fn test_seed_generator() {
+        let seed1 = generate_random_seed(&["falcon512".to_string()], &[]).unwrap();
+        let seed2 = generate_random_seed(&["falcon512".to_string()], &[]).unwrap();
+        assert_ne!(&seed1, &seed2);
+        assert_eq!(&seed1[4..7], &[1, 0, 0]);
+        assert_eq!(&seed2[4..7], &[1, 0, 0]);
+    }
+
+    #[test]
+    fn test_seed_generator_with_rng() {
+        let mapping = get_name_to_ref_mapping();
+        let names: Vec<String> = mapping.keys().cloned().collect();
+        let seed1 = generate_random_seed_with_rng(&names, &[], &mut StdRng::seed_from_u64(42)).unwrap();
+        let seed2 = generate_random_seed_with_rng(&names, &[], &mut StdRng::seed_from_u64(42)).unwrap();
+        let seed3 = generate_random_seed_with_rng(&names, &[], &mut StdRng::seed_from_u64(43)).unwrap();
+        assert_eq!(seed1, seed2);
+        assert_ne!(seed1, seed3);
+        assert_eq!(parse_seed(&seed1).unwrap().signature_scheme_ids, parse_seed(&seed3).unwrap().signature_scheme_ids);
+    }
+
+    fn legacy_seed(signature_scheme_ids: &[(u16, u16)], key_encapsulation_scheme_ids: &[(u16, u16)]) -> Vec<u8> {
+        let mut seed = vec![signature_scheme_ids.len() as u8];
+        for scheme_id in signature_scheme_ids.iter().chain(key_encapsulation_scheme_ids.iter()) {
+            seed.push(scheme_id.0 as u8);
+            seed.push(scheme_id.1 as u8);
+        }
+        seed.extend(test_seed);
+        return seed;
+    }
+
+    // Signature schemes whose ids encode and branch the same in every seed and key format
+    fn narrow_signature_names() -> Vec<String> {
+        return get_name_to_ref_mapping()
+            .iter()
+            .filter(|(_, scheme_ref)| scheme_ref.scheme_id < 0x80 && scheme_ref.scheme_config_id < 0x80)
+            .map(|(name, _)| name.clone())
+            .collect();
+    }
+
+    #[test]
+    fn test_legacy_seed_migration() {
+        let names = narrow_signature_names();
+        let seed = wrap_seed(&names, &[], test_seed).unwrap();
+        let parsed_seed = parse_seed(&seed).unwrap();
+        assert_eq!(parsed_seed.version, SEED_VERSION);
+
+        let legacy = legacy_seed(&parsed_seed.signature_scheme_ids, &[(0, 0)]);
+        let parsed_legacy = parse_seed(&legacy).unwrap();
+        assert_eq!(parsed_legacy.version, LEGACY_SEED_VERSION);
+        assert_eq!(parsed_legacy.seed.as_bytes(), test_seed);
+        assert_eq!(parsed_legacy.signature_scheme_ids, parsed_seed.signature_scheme_ids);
+        assert_eq!(parsed_legacy.key_encapsulation_scheme_ids, vec![(0, 0)]);
+
+        let legacy = legacy_seed(&parsed_seed.signature_scheme_ids, &[]);
+        assert_eq!(migrate_seed(&legacy).unwrap(), seed);
+        // Migration keeps the component keys, only the combined public key gains a header
+        let legacy_public_key = generate_combined_public_key(Signature, &legacy, KeyGenerationOptions::default()).unwrap();
+        let public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+        assert_eq!(read_combined_public_key_header(&legacy_public_key).unwrap().0, None);
+        assert_eq!(parse_combined_public_key(Signature, &legacy_public_key).unwrap().encoding, ComponentEncoding::Legacy);
+        assert_eq!(parse_combined_public_key(Signature, &legacy_public_key).unwrap().components, parse_combined_public_key(Signature, &public_key).unwrap().components);
+        assert!(verify_message(b"CAFEBABE", &legacy_public_key, &sign_message(&legacy, b"CAFEBABE").unwrap()));
+    }
+
+    #[test]
+    fn test_variable_entropy_lengths() {
+        let mapping = get_name_to_ref_mapping();
+        let names: Vec<String> = mapping.keys().cloned().collect();
+        let entropy = [test_seed.to_vec(), test_seed.to_vec()].concat();
+        for entropy_len in MIN_SEED_ENTROPY_LEN..=MAX_SEED_ENTROPY_LEN {
+            let seed = wrap_seed(&names, &[], &entropy[..entropy_len]).unwrap();
+            let parsed_seed = parse_seed(&seed).unwrap();
+            assert_eq!(parsed_seed.seed.as_bytes(), &entropy[..entropy_len]);
+            let combined_public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+            let signature = sign_message(&seed, b"CAFEBABE").unwrap();
+            assert!(verify_message(b"CAFEBABE", &combined_public_key, &signature));
+        }
+        let seed = generate_random_seed_with_entropy_len(&names, &[], MAX_SEED_ENTROPY_LEN, &mut StdRng::seed_from_u64(42)).unwrap();
+        assert_eq!(parse_seed(&seed).unwrap().seed.len(), MAX_SEED_ENTROPY_LEN);
+    }
+
+    #[test]
+    fn test_too_short_entropy() {
+        assert!(wrap_seed(&[], &[], &test_seed[..MIN_SEED_ENTROPY_LEN - 1]).is_err());
+        assert!(HybridSeed::wrap(&[], &[], &test_seed[..MIN_SEED_ENTROPY_LEN - 1]).is_err());
+        assert!(wrap_seed(&["no such scheme".to_string()], &[], test_seed).is_err());
+        assert!(generate_random_seed_with_entropy_len(&[], &[], MAX_SEED_ENTROPY_LEN + 1, &mut StdRng::seed_from_u64(42)).is_err());
+    }
+
+    #[test]
+    fn test_version_1_seed_derives_same_keys() {
+        let names = narrow_signature_names();
+        let seed = wrap_seed(&names, &[], test_seed).unwrap();
+        let parsed_seed = parse_seed(&seed).unwrap();
+        // Rebuild the seed in the version 1 layout, which has no entropy length
+        let entropy_len_idx = seed.len() - SEED_CHECKSUM_LEN - SEED_ENTROPY_LEN - 1;
+        let mut version_1_seed = seed[..entropy_len_idx].to_vec();
+        version_1_seed[SEED_MAGIC.len()] = SEED_VERSION_FIXED_ENTROPY;
+        version_1_seed.extend(test_seed);
+        let checksum = blake3::Hasher::new().update(&[2]).update(&version_1_seed).finalize();
+        version_1_seed.extend(&checksum.as_bytes()[..SEED_CHECKSUM_LEN]);
+        let parsed_version_1_seed = parse_seed(&version_1_seed).unwrap();
+        assert_eq!(parsed_version_1_seed.version, SEED_VERSION_FIXED_ENTROPY);
+        assert_eq!(parsed_version_1_seed.seed, parsed_seed.seed);
+        assert_eq!(parsed_version_1_seed.signature_scheme_ids, parsed_seed.signature_scheme_ids);
+        assert_eq!(migrate_seed(&version_1_seed).unwrap(), seed);
+    }
+
+    #[test]
+    fn test_version_2_seed_derives_same_keys() {
+        let names = narrow_signature_names();
+        let seed = wrap_seed(&names, &[], test_seed).unwrap();
+        // Ids below 128 are encoded the same in both layouts, only the version differs
+        let mut version_2_seed = seed[..seed.len() - SEED_CHECKSUM_LEN].to_vec();
+        version_2_seed[SEED_MAGIC.len()] = SEED_VERSION_SINGLE_BYTE_IDS;
+        let checksum = seed_checksum(&version_2_seed);
+        version_2_seed.extend(checksum);
+        let parsed_version_2_seed = parse_seed(&version_2_seed).unwrap();
+        assert_eq!(parsed_version_2_seed.version, SEED_VERSION_SINGLE_BYTE_IDS);
+        assert_eq!(parsed_version_2_seed.signature_scheme_ids, parse_seed(&seed).unwrap().signature_scheme_ids);
+        let version_2_public_key = generate_combined_public_key(Signature, &version_2_seed, KeyGenerationOptions::default()).unwrap();
+        let public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+        assert_eq!(read_combined_public_key_header(&version_2_public_key).unwrap().0, None);
+        assert_eq!(parse_combined_public_key(Signature, &version_2_public_key).unwrap().components, parse_combined_public_key(Signature, &public_key).unwrap().components);
+        assert_eq!(migrate_seed(&version_2_seed).unwrap(), seed);
+    }
+
+    #[test]
+    fn test_wide_scheme_ids_in_seed() {
+        let mut seed = SEED_MAGIC.to_vec();
+        seed.push(SEED_VERSION);
+        seed.extend([2, 0xac, 0x02, 0x02, 0x01, 0xc8, 0x01]); // (300, 2) and (1, 200)
+        seed.extend([1, 0x80, 0x80, 0x01, 0xff, 0xff, 0x03]); // (16384, 65535)
+        seed.push(test_seed.len() as u8);
+        seed.extend(test_seed);
+        let checksum = seed_checksum(&seed);
+        seed.extend(checksum);
+        let parsed_seed = parse_seed(&seed).unwrap();
+        assert_eq!(parsed_seed.signature_scheme_ids, vec![(300, 2), (1, 200)]);
+        assert_eq!(parsed_seed.key_encapsulation_scheme_ids, vec![(16384, 65535)]);
+        assert_eq!(migrate_seed(&seed).unwrap(), seed);
+        // Wide ids get their own seed branches
+        assert_ne!(
+            create_scheme_seed_branch(&parsed_seed.seed, Signature, 300, 2),
+            create_scheme_seed_branch(&parsed_seed.seed, Signature, 300 % 256, 2)
+        );
+        // Ids below 128 keep the legacy seed branches, larger ids only keep them in older seeds
+        assert_eq!(
+            create_scheme_seed_branch(&parsed_seed.seed, Signature, 1, 0),
+            create_legacy_scheme_seed_branch(&parsed_seed.seed, Signature, 1, 0)
+        );
+        assert_ne!(
+            create_scheme_seed_branch(&parsed_seed.seed, Signature, 200, 1),
+            create_legacy_scheme_seed_branch(&parsed_seed.seed, Signature, 200, 1)
+        );
+        let mut version_2_seed = SEED_MAGIC.to_vec();
+        version_2_seed.push(SEED_VERSION_SINGLE_BYTE_IDS);
+        version_2_seed.extend([1, 200, 1, 0]); // (200, 1)
+        version_2_seed.push(test_seed.len() as u8);
+        version_2_seed.extend(test_seed);
+        let checksum = seed_checksum(&version_2_seed);
+        version_2_seed.extend(checksum);
+        let parsed_version_2_seed = parse_seed(&version_2_seed).unwrap();
+        assert_eq!(parsed_version_2_seed.signature_scheme_ids, vec![(200, 1)]);
+        assert_eq!(parsed_version_2_seed.encoding(), ComponentEncoding::Legacy);
+        assert_eq!(
+            parsed_version_2_seed.scheme_seed_branch(Signature, (200, 1)).unwrap(),
+            create_legacy_scheme_seed_branch(&parsed_version_2_seed.seed, Signature, 200, 1)
+        );
+        assert!(parse_seed(&seed).unwrap().scheme_seed_branch(Signature, (300, 2)).is_ok());
+        assert!(parsed_version_2_seed.scheme_seed_branch(Signature, (300, 2)).is_err());
+        // Migrating would silently change its keys
+        assert!(migrate_seed(&version_2_seed).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_schemes_in_seed_are_rejected() {
+        let names = vec!["test-fixed".to_string(), "test-variable".to_string(), "test-fixed".to_string()];
+        assert!(wrap_seed(&names, &[], test_seed).is_err());
+        assert!(HybridSeed::wrap(&names, &[], &test_seed).is_err());
+        let duplicate = legacy_seed(&[(200, 0), (200, 0)], &[]);
+        assert!(parse_seed(&duplicate).is_err());
+        assert!(HybridSeed::from_bytes(&duplicate).is_err());
+        assert!(generate_combined_public_key(Signature, &duplicate, KeyGenerationOptions::default()).is_err());
+        // The same scheme may still sign and encapsulate, those are separate lists
+        assert!(parse_seed(&legacy_seed(&[(0, 0)], &[(0, 0)])).is_ok());
+    }
+
+    #[test]
+    fn test_corrupted_seed_is_rejected() {
+        let names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let seed = wrap_seed(&names, &[], test_seed).unwrap();
+        // Every byte counts, including the magic, which must not let a seed pass for a legacy one
+        for idx in 0..seed.len() {
+            for flip in [0x01, 0x80, 0xff] {
+                let mut flipped = seed.clone();
+                flipped[idx] ^= flip;
+                assert!(parse_seed(&flipped).is_err(), "byte {} flipped with {:#x} was accepted", idx, flip);
+                if idx < SEED_MAGIC.len() {
+                    assert_eq!(parse_seed(&flipped).err(), Some("Seed magic is damaged".to_string()));
+                }
+            }
+            let mut dropped = seed.clone();
+            dropped.remove(idx);
+            assert!(parse_seed(&dropped).is_err(), "dropped byte {} was accepted", idx);
+        }
+        assert!(parse_seed(&seed[..10]).is_err());
+        assert!(parse_seed(&[]).is_err());
+    }
+
+    #[test]
+    fn test_all_signature_algorithms() {
+        let mapping = get_name_to_ref_mapping();
+        let test_msg = hex!("CAFEBABE");
+        for (name, scheme_ref) in mapping.iter() {
+            info!("Testing {}...", name);
+            let seed = wrap_seed(&[name.to_string()], &[], test_seed).unwrap();
+            let signature = sign_message(&seed, &test_msg).unwrap();
+            debug!("Signature: {}", hex::encode(&signature));
+            let combined_public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+            let is_valid = verify_message(&test_msg, &combined_public_key, &signature);
+            assert_eq!(is_valid, true);
+            let mut test_msg_increased = test_msg.clone();
+            increment_bytes(&mut test_msg_increased, 1);
+            let is_valid = verify_message(&test_msg_increased, &combined_public_key, &signature);
+            assert_eq!(is_valid, false);
+            // Schemes that take their randomness from the rng must be reproducible with it
+            if let Ok(rng_signature) = sign_message_with_rng(&seed, &test_msg, &mut StdRng::seed_from_u64(42)) {
+                assert!(verify_message(&test_msg, &combined_public_key, &rng_signature));
+                assert_eq!(sign_message_with_rng(&seed, &test_msg, &mut StdRng::seed_from_u64(42)), Ok(rng_signature));
+            }
+        }
+    }
+
+    #[test]
+    fn test_combined_public_key_header() {
+        let signature_names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let key_encapsulation_names: Vec<String> = key_encapsulation::get_name_to_ref_mapping().keys().cloned().collect();
+        let seed = wrap_seed(&signature_names, &key_encapsulation_names, test_seed).unwrap();
+        let test_msg = hex!("CAFEBABE");
+
+        // Current seeds make keys with a version 2 header
+        let with_header = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+        assert_eq!(generate_combined_public_key_with_header(Signature, &seed).unwrap(), with_header);
+        let (header, _) = read_combined_public_key_header(&with_header).unwrap();
+        assert_eq!(header, Some(CombinedPublicKeyHeader { version: COMBINED_PUBLIC_KEY_VERSION, purpose: Signature, component_count: signature_names.len() as u32 }));
+        assert_eq!(parse_self_describing_combined_public_key(&with_header).unwrap().0, Signature);
+        assert!(add_combined_public_key_header(Signature, &with_header).is_err());
+
+        // Legacy seeds make keys without header, which get a version 1 header
+        let parsed_seed = parse_seed(&seed).unwrap();
+        let legacy = legacy_seed(&parsed_seed.signature_scheme_ids, &parsed_seed.key_encapsulation_scheme_ids);
+        let combined_public_key = generate_combined_public_key(Signature, &legacy, KeyGenerationOptions::default()).unwrap();
+        let legacy_with_header = generate_combined_public_key_with_header(Signature, &legacy).unwrap();
+        assert_eq!(&legacy_with_header[COMBINED_PUBLIC_KEY_HEADER_LEN..], &combined_public_key[..]);
+        let (header, body) = read_combined_public_key_header(&legacy_with_header).unwrap();
+        assert_eq!(header, Some(CombinedPublicKeyHeader { version: COMBINED_PUBLIC_KEY_VERSION_SINGLE_BYTE_COUNT, purpose: Signature, component_count: signature_names.len() as u32 }));
+        assert_eq!(body, &combined_public_key[..]);
+        assert!(parse_self_describing_combined_public_key(&combined_public_key).is_err());
+        let legacy_signature = sign_message(&legacy, &test_msg).unwrap();
+        assert!(verify_message(&test_msg, &combined_public_key, &legacy_signature));
+        assert!(verify_message(&test_msg, &legacy_with_header, &legacy_signature));
+        // and keep the address of the key without header
+        assert_eq!(hash_combined_public_key(&legacy_with_header), hash_combined_public_key(&combined_public_key));
+
+        // Version 2 headers put the purpose into the address
+        assert_ne!(hash_combined_public_key(&with_header), hash_combined_public_key(&combined_public_key));
+        let mut other_purpose = with_header.clone();
+        other_purpose[COMBINED_PUBLIC_KEY_MAGIC.len() + 1] = 1;
+        assert_ne!(hash_combined_public_key(&other_purpose), hash_combined_public_key(&with_header));
+        let mut bad_header = with_header.clone();
+        bad_header[COMBINED_PUBLIC_KEY_MAGIC.len()] = 0;
+        assert!(hash_combined_public_key(&bad_header).is_err());
+
+        let signature = sign_message(&seed, &test_msg).unwrap();
+        assert!(verify_message(&test_msg, &with_header, &signature));
+
+        // A key encapsulation key can never pass for a signature key
+        let key_encapsulation_key = generate_combined_public_key_with_header(KeyEncapsulation, &seed).unwrap();
+        assert_eq!(parse_self_describing_combined_public_key(&key_encapsulation_key).unwrap().0, KeyEncapsulation);
+        assert!(parse_combined_public_key(Signature, &key_encapsulation_key).is_err());
+        assert!(!verify_message(&test_msg, &key_encapsulation_key, &signature));
+
+        // The component count has to match
+        let mut wrong_count = with_header.clone();
+        wrong_count[COMBINED_PUBLIC_KEY_HEADER_LEN - 1] += 1;
+        assert!(parse_combined_public_key(Signature, &wrong_count).is_err());
+        let mut wrong_version = with_header.clone();
+        wrong_version[COMBINED_PUBLIC_KEY_MAGIC.len()] = 0;
+        assert!(parse_combined_public_key(Signature, &wrong_version).is_err());
+    }
+
+    #[test]
+    fn test_canonical_combined_public_key() {
+        let signature_names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let key_encapsulation_names: Vec<String> = key_encapsulation::get_name_to_ref_mapping().keys().cloned().collect();
+        let seed = wrap_seed(&signature_names, &key_encapsulation_names, test_seed).unwrap();
+        for purpose in [Signature, KeyEncapsulation] {
+            let combined_public_key = generate_combined_public_key(purpose, &seed, KeyGenerationOptions::default()).unwrap();
+            let parsed = parse_canonical_combined_public_key(purpose, &combined_public_key).unwrap();
+            assert_eq!(parsed.encode(), Ok(combined_public_key.clone()));
+            assert!(is_canonical_combined_public_key(purpose, &generate_combined_public_key_with_header(purpose, &seed).unwrap()));
+            if parsed.components.is_empty() {
+                continue;
+            }
+
+            // Duplicated components are ambiguous
+            let duplicated = [combined_public_key.clone(), combined_public_key.clone()].concat();
+            assert!(parse_combined_public_key(purpose, &duplicated).is_err());
+            // Dangling bytes are not silently ignored
+            let mut dangling = combined_public_key.clone();
+            dangling.push(parsed.components[0].0 .0 as u8);
+            assert!(parse_combined_public_key(purpose, &dangling).is_err());
+        }
+
+        let unsorted = ParsedCombinedPublicKey::new(Signature, ComponentEncoding::Varint, vec![((1, 0), vec![]), ((0, 0), vec![])]);
+        assert!(!unsorted.is_sorted());
+        assert_eq!(unsorted.scheme_ids(), vec![(1, 0), (0, 0)]);
+        assert_eq!(unsorted.get(&(0, 0)), Some(&[][..]));
+        assert_eq!(unsorted.get(&(2, 0)), None);
+    }
+
+    #[test]
+    fn test_ciphertext_follows_public_key_order() {
+        let key_encapsulation_names: Vec<String> = key_encapsulation::get_name_to_ref_mapping().keys().cloned().collect();
+        let seed = wrap_seed(&[], &key_encapsulation_names, test_seed).unwrap();
+        let combined_public_key = generate_combined_public_key(KeyEncapsulation, &seed, KeyGenerationOptions::default()).unwrap();
+        let parsed = parse_combined_public_key(KeyEncapsulation, &combined_public_key).unwrap();
+        let (_, ciphertext) = encapsulate(&combined_public_key).unwrap();
+        let mut idx = 0;
+        for (scheme_id, _) in parsed.components.iter() {
+            assert_eq!(ComponentEncoding::Varint.decode_scheme_id(&ciphertext, &mut idx), Ok(*scheme_id));
+            let scheme_ref = key_encapsulation::get_id_to_ref_mapping();
+            let ct_len = scheme_ref.get(scheme_id).unwrap().get_scheme_info().ct_size_info.fixed_size.unwrap() as usize;
+            idx += ct_len;
+        }
+        assert_eq!(idx, ciphertext.len());
+    }
+
+    #[test]
+    fn test_signature_follows_public_key_order() {
+        let test_msg = hex!("CAFEBABE");
+        // Legacy keys and signatures keep the order of the seed
+        let seed = legacy_seed(&[(200, 1), (200, 0)], &[]);
+        let combined_public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+        let parsed = parse_combined_public_key(Signature, &combined_public_key).unwrap();
+        assert_eq!(parsed.encoding, ComponentEncoding::Legacy);
+        assert_eq!(parsed.scheme_ids(), vec![(200, 1), (200, 0)]);
+        #[allow(deprecated)]
+        let id_mapping_len = parsed.id_mapping.len();
+        assert_eq!(id_mapping_len, 2);
+        let signature = sign_message(&seed, &test_msg).unwrap();
+        assert_eq!(HybridSignature::from_bytes(&signature).unwrap().scheme_ids(), vec![(200, 1), (200, 0)]);
+        assert!(verify_message_strict(&test_msg, &combined_public_key, &signature));
+
+        // Current seeds sort the components by scheme id
+        let seed = wrap_seed(&["test-variable".to_string(), "test-fixed".to_string()], &[], test_seed).unwrap();
+        let combined_public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+        let parsed = parse_combined_public_key(Signature, &combined_public_key).unwrap();
+        assert_eq!(parsed.encoding, ComponentEncoding::Varint);
+        assert_eq!(parsed.scheme_ids(), vec![(200, 0), (200, 1)]);
+        let signature = sign_message(&seed, &test_msg).unwrap();
+        assert_eq!(HybridSignature::from_bytes(&signature).unwrap().scheme_ids(), vec![(200, 0), (200, 1)]);
+        assert!(verify_message_strict(&test_msg, &combined_public_key, &signature));
+
+        // Version 2 keys with unsorted components are rejected
+        let mut swapped = Vec::new();
+        push_combined_public_key_header(&mut swapped, Signature, ComponentEncoding::Varint, 2).unwrap();
+        for (scheme_id, pk) in parsed.components.iter().rev() {
+            ComponentEncoding::Varint.encode_scheme_id(*scheme_id, &mut swapped).unwrap();
+            swapped.extend(pk);
+        }
+        assert!(parse_combined_public_key(Signature, &swapped).is_err());
+    }
+
+    #[test]
+    fn test_unknown_scheme_ids_are_rejected() {
+        let test_msg = hex!("CAFEBABE");
+        let seed = wrap_seed(&["test-fixed".to_string()], &[], test_seed).unwrap();
+        let combined_public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+        let signature = sign_message(&seed, &test_msg).unwrap();
+        // Components of schemes that aren't compiled in fail verification instead of panicking
+        let unknown_component = [100, 100, 1, 2, 3];
+        for forged in [unknown_component.to_vec(), [signature.clone(), unknown_component.to_vec()].concat()] {
+            assert!(!verify_message(&test_msg, &combined_public_key, &forged));
+            assert!(!verify_message_strict(&test_msg, &combined_public_key, &forged));
+        }
+        assert!(sign_message(&legacy_seed(&[(100, 100)], &[]), &test_msg).is_err());
+        // Likewise for ciphertexts, including ones that are cut short
+        assert!(decapsulate(&seed, &unknown_component).is_err());
+        assert!(decapsulate(&seed, &[0]).is_err());
+        assert!(decapsulate(&legacy_seed(&[], &[(100, 100)]), &unknown_component).is_err());
+    }
+
+    #[test]
+    fn test_truncated_signature_is_rejected() {
+        let test_msg = hex!("CAFEBABE");
+        for name in get_name_to_ref_mapping().keys() {
+            let seed = wrap_seed(&[name.to_string()], &[], test_seed).unwrap();
+            let combined_public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+            let signature = sign_message(&seed, &test_msg).unwrap();
+            for len in 1..signature.len() {
+                assert!(!verify_message(&test_msg, &combined_public_key, &signature[..len]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_strict_verification() {
+        let signature_names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let seed = wrap_seed(&signature_names, &[], test_seed).unwrap();
+        let combined_public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+        let test_msg = hex!("CAFEBABE");
+        let signature = sign_message(&seed, &test_msg).unwrap();
+        assert!(verify_message_strict(&test_msg, &combined_public_key, &signature));
+        if signature_names.is_empty() {
+            return;
+        }
+        assert!(!verify_message_strict(b"other message", &combined_public_key, &signature));
+        // An empty signature passes the lenient check but covers none of the components
+        assert!(verify_message(&test_msg, &combined_public_key, &[]));
+        assert!(!verify_message_strict(&test_msg, &combined_public_key, &[]));
+        // Nor may a component be repeated to make up for a missing one
+        let repeated = [signature.clone(), signature.clone()].concat();
+        assert!(!verify_message_strict(&test_msg, &combined_public_key, &repeated));
+        let typed_signature = HybridSignature::from_bytes(&signature).unwrap();
+        assert_eq!(typed_signature.scheme_ids().len(), signature_names.len());
+        let public_key = SignaturePublicKey::from_bytes(&combined_public_key).unwrap();
+        assert!(public_key.verify(&test_msg, &typed_signature));
+    }
+
+    #[test]
+    fn test_typed_api() {
+        let signature_names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let key_encapsulation_names: Vec<String> = key_encapsulation::get_name_to_ref_mapping().keys().cloned().collect();
+        let seed = HybridSeed::wrap(&signature_names, &key_encapsulation_names, &test_seed).unwrap();
+        assert_eq!(HybridSeed::from_bytes(seed.as_bytes()), Ok(seed.clone()));
+        assert!(HybridSeed::from_bytes(&test_seed).is_err());
+        let test_msg = hex!("CAFEBABE");
+
+        let signature_public_key = seed.signature_public_key().unwrap();
+        assert_eq!(signature_public_key.as_ref(), &generate_combined_public_key(Signature, seed.as_bytes(), KeyGenerationOptions::default()).unwrap()[..]);
+        assert_eq!(signature_public_key.address().as_ref(), &generate_address(Signature, seed.as_bytes()).unwrap()[..]);
+        let signature = seed.sign(&test_msg).unwrap();
+        assert!(signature_public_key.verify(&test_msg, &signature));
+        assert_eq!(HybridSignature::from_bytes(signature.as_bytes()), Ok(signature.clone()));
+        if !signature.as_bytes().is_empty() {
+            assert!(HybridSignature::from_bytes(&signature.as_bytes()[1..]).is_err());
+        }
+
+        let key_encapsulation_public_key = seed.key_encapsulation_public_key().unwrap();
+        assert_eq!(key_encapsulation_public_key.purpose(), KeyEncapsulation);
+        let (shared_secret, ciphertext) = key_encapsulation_public_key.encapsulate().unwrap();
+        assert_eq!(HybridCiphertext::from_bytes(ciphertext.as_bytes()), Ok(ciphertext.clone()));
+        assert_eq!(seed.decapsulate(&ciphertext).unwrap(), shared_secret);
+
+        // Keys of one purpose don't parse as the other
+        if !signature_names.is_empty() {
+            assert!(!signature_public_key.verify(b"other message", &signature));
+            assert!(KeyEncapsulationPublicKey::from_bytes(signature_public_key.as_bytes()).is_err());
+        }
+        assert!(SignaturePublicKey::from_bytes(&test_seed).is_err());
+        assert!(SignatureAddress::from_bytes(&test_seed).is_err());
+        assert!(SharedSecret::from_bytes(&test_seed).is_err());
+
+        // verify needs every component of a non-empty key, verify_lenient accepts any subset
+        let empty_signature = HybridSignature::from_bytes(&[]).unwrap();
+        assert!(signature_public_key.verify_lenient(&test_msg, &empty_signature));
+        assert!(!signature_public_key.verify(&test_msg, &empty_signature));
+        let empty_public_key = HybridSeed::wrap(&[], &[], &test_seed).unwrap().signature_public_key().unwrap();
+        assert!(!empty_public_key.verify(&test_msg, &empty_signature));
+
+        // Seeds naming schemes that aren't compiled in are rejected up front
+        let unknown_scheme_seed = legacy_seed(&[(250, 250)], &[]);
+        assert!(parse_seed(&unknown_scheme_seed).is_ok());
+        assert!(HybridSeed::from_bytes(&unknown_scheme_seed).is_err());
+
+        // Decapsulating a ciphertext for schemes the seed doesn't hold fails instead of panicking
+        if !key_encapsulation_names.is_empty() {
+            let signature_seed = HybridSeed::wrap(&signature_names, &[], &test_seed).unwrap();
+            assert!(signature_seed.decapsulate(&ciphertext).is_err());
+            assert!(HybridKeyBundle::from_seed(&signature_seed).unwrap().decapsulate(&ciphertext).is_err());
+        }
+    }
+
+    #[test]
+    fn test_key_bundle() {
+        let signature_names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let key_encapsulation_names: Vec<String> = key_encapsulation::get_name_to_ref_mapping().keys().cloned().collect();
+        let seed = HybridSeed::wrap(&signature_names, &key_encapsulation_names, &test_seed).unwrap();
+        let test_msg = hex!("CAFEBABE");
+
+        // A bundle derived from a seed is interchangeable with the seed
+        let from_seed = HybridKeyBundle::from_seed(&seed).unwrap();
+        assert_eq!(from_seed.signature_public_key(), seed.signature_public_key().unwrap());
+        assert_eq!(from_seed.key_encapsulation_public_key(), seed.key_encapsulation_public_key().unwrap());
+        assert!(seed.signature_public_key().unwrap().verify(&test_msg, &from_seed.sign(&test_msg).unwrap()));
+        let (shared_secret, ciphertext) = seed.key_encapsulation_public_key().unwrap().encapsulate().unwrap();
+        assert_eq!(from_seed.decapsulate(&ciphertext).unwrap(), shared_secret);
+
+        // Keypairs that were never derived from a seed
+        let mut other_seed = test_seed.to_vec();
+        increment_bytes(&mut other_seed, 1);
+        let mut bundle = HybridKeyBundle::new();
+        for (name, scheme_ref) in get_name_to_ref_mapping().iter() {
+            let mismatched = Keypair {
+                pk: scheme_ref.scheme_impl.generate_keypair(&test_seed).pk.clone(),
+                sk: scheme_ref.scheme_impl.generate_keypair(&other_seed).sk.clone(),
+            };
+            assert!(bundle.add_signature_keypair(name, mismatched).is_err());
+            let mut truncated = scheme_ref.scheme_impl.generate_keypair(&other_seed);
+            truncated.sk.pop();
+            assert!(bundle.add_signature_keypair(name, truncated).is_err());
+            assert_eq!(bundle.add_signature_keypair(name, scheme_ref.scheme_impl.generate_keypair(&other_seed)), Ok(()));
+            assert!(bundle.add_signature_keypair(name, scheme_ref.scheme_impl.generate_keypair(&other_seed)).is_err());
+        }
+        for (name, scheme_ref) in key_encapsulation::get_name_to_ref_mapping().iter() {
+            let mut truncated = scheme_ref.scheme_impl.generate_keypair(&other_seed);
+            truncated.sk.pop();
+            assert!(bundle.add_key_encapsulation_keypair(name, truncated).is_err());
+            assert_eq!(bundle.add_key_encapsulation_keypair(name, scheme_ref.scheme_impl.generate_keypair(&other_seed)), Ok(()));
+        }
+        assert!(bundle.add_signature_keypair("unknown", Keypair { pk: vec![], sk: vec![] }).is_err());
+
+        let signature_public_key = bundle.signature_public_key();
+        assert_eq!(signature_public_key.parse().component_count(), signature_names.len());
+        assert!(signature_public_key.verify(&test_msg, &bundle.sign(&test_msg).unwrap()));
+        assert!(verify_message(&test_msg, signature_public_key.as_bytes(), bundle.sign(&test_msg).unwrap().as_bytes()));
+        let (shared_secret, ciphertext) = bundle.key_encapsulation_public_key().encapsulate().unwrap();
+        assert_eq!(bundle.decapsulate(&ciphertext).unwrap(), shared_secret);
+        if !signature_names.is_empty() {
+            assert_ne!(signature_public_key, seed.signature_public_key().unwrap());
+            assert!(!signature_public_key.verify(&test_msg, &seed.sign(&test_msg).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_hybrid_certificate_chain() {
+        let signature_names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let key_encapsulation_names: Vec<String> = key_encapsulation::get_name_to_ref_mapping().keys().cloned().collect();
+        let seed_with_offset = |offset: u64| {
+            let mut entropy = test_seed.to_vec();
+            increment_bytes(&mut entropy, offset);
+            return HybridSeed::wrap(&signature_names, &key_encapsulation_names, &entropy).unwrap();
+        };
+        let (root, intermediate, leaf) = (seed_with_offset(0), seed_with_offset(1), seed_with_offset(2));
+        let now = 1_700_000_000;
+        if signature_names.is_empty() {
+            assert!(HybridCertificate::self_signed(&root, now, now, KEY_USAGE_CERTIFY).is_err());
+            return;
+        }
+
+        let root_certificate = HybridCertificate::self_signed(&root, now - 10, now + 10, KEY_USAGE_CERTIFY).unwrap();
+        let intermediate_certificate = HybridCertificate::issue(&root, Some(intermediate.signature_public_key().unwrap()), None, now - 10, now + 10, KEY_USAGE_CERTIFY).unwrap();
+        let leaf_key_encapsulation_public_key = match key_encapsulation_names.is_empty() {
+            true => None,
+            false => Some(leaf.key_encapsulation_public_key().unwrap()),
+        };
+        let leaf_key_usage = match key_encapsulation_names.is_empty() {
+            true => KEY_USAGE_SIGN,
+            false => KEY_USAGE_SIGN | KEY_USAGE_ENCAPSULATE,
+        };
+        let leaf_certificate = HybridCertificate::issue(&intermediate, Some(leaf.signature_public_key().unwrap()), leaf_key_encapsulation_public_key, now - 10, now + 10, leaf_key_usage).unwrap();
+        assert_eq!(leaf_certificate.issuer, intermediate.signature_public_key().unwrap().address());
+        assert_eq!(HybridCertificate::from_bytes(&leaf_certificate.encode()), Ok(leaf_certificate.clone()));
+
+        let chain = [leaf_certificate.clone(), intermediate_certificate.clone(), root_certificate.clone()];
+        let trusted_roots = [root.signature_public_key().unwrap().address()];
+        assert_eq!(verify_hybrid_certificate_chain(&chain, &trusted_roots, now), Ok(()));
+        assert!(verify_hybrid_certificate_chain(&chain, &trusted_roots, now + 11).is_err());
+        assert!(verify_hybrid_certificate_chain(&chain, &[intermediate.signature_public_key().unwrap().address()], now).is_err());
+        assert!(verify_hybrid_certificate_chain(&chain[..2], &trusted_roots, now).is_err());
+        assert!(verify_hybrid_certificate_chain(&[leaf_certificate.clone(), root_certificate.clone()], &trusted_roots, now).is_err());
+
+        // Only keys allowed to certify may issue, and usage flags need a matching key
+        let signing_only = HybridCertificate::issue(&root, Some(intermediate.signature_public_key().unwrap()), None, now - 10, now + 10, KEY_USAGE_SIGN).unwrap();
+        assert!(verify_hybrid_certificate_chain(&[leaf_certificate.clone(), signing_only, root_certificate], &trusted_roots, now).is_err());
+        assert!(HybridCertificate::issue(&root, None, None, now, now, 0).is_err());
+        assert!(HybridCertificate::issue(&root, Some(leaf.signature_public_key().unwrap()), None, now, now, KEY_USAGE_ENCAPSULATE).is_err());
+        assert!(HybridCertificate::issue(&root, Some(leaf.signature_public_key().unwrap()), None, now + 1, now, KEY_USAGE_SIGN).is_err());
+
+        // Changing a signed field breaks the signature
+        let mut tampered = leaf_certificate.clone();
+        tampered.not_after += 1;
+        assert!(!tampered.is_signed_by(&intermediate.signature_public_key().unwrap()));
+        let encoded = leaf_certificate.encode();
+        assert!(HybridCertificate::from_bytes(&encoded[..encoded.len() - 1]).is_err());
+        assert!(HybridCertificate::from_bytes(&[encoded.clone(), vec![0]].concat()).is_err());
+    }
+
+    #[test]
+    fn test_verify_with_address() {
+        let signature_names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let seed = HybridSeed::wrap(&signature_names, &[], &test_seed).unwrap();
+        let mut other_entropy = test_seed.to_vec();
+        increment_bytes(&mut other_entropy, 1);
+        let other_seed = HybridSeed::wrap(&signature_names, &[], &other_entropy).unwrap();
+        let address = SignatureAddress::from_bytes(&generate_address(Signature, seed.as_bytes()).unwrap()).unwrap();
+        let test_msg = hex!("CAFEBABE");
+
+        let bundle = SignatureWithPublicKey::sign(&seed, &test_msg).unwrap();
+        assert_eq!(SignatureWithPublicKey::from_bytes(&bundle.encode()), Ok(bundle.clone()));
+        assert_eq!(verify_with_address(&test_msg, &bundle, &address), Ok(()));
+        let encoded = bundle.encode();
+        assert!(SignatureWithPublicKey::from_bytes(&encoded[..encoded.len() - 1]).is_err());
+        if signature_names.is_empty() {
+            return;
+        }
+        assert_eq!(verify_with_address(b"other message", &bundle, &address), Err(AddressVerificationError::InvalidSignature));
+        // A valid signature by another key is still rejected for this address
+        let other_bundle = SignatureWithPublicKey::sign(&other_seed, &test_msg).unwrap();
+        assert_eq!(verify_with_address(&test_msg, &other_bundle, &address), Err(AddressVerificationError::AddressMismatch));
+        let swapped_signature = SignatureWithPublicKey {
+            public_key: bundle.public_key.clone(),
+            signature: other_bundle.signature.clone(),
+        };
+        assert_eq!(verify_with_address(&test_msg, &swapped_signature, &address), Err(AddressVerificationError::InvalidSignature));
+    }
+
+    #[test]
+    fn test_multisig() {
+        let signature_names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let signers: Vec<HybridSeed> = (0..3).map(|offset| {
+            let mut entropy = test_seed.to_vec();
+            increment_bytes(&mut entropy, offset);
+            return HybridSeed::wrap(&signature_names, &[], &entropy).unwrap();
+        }).collect();
+        let public_keys: Vec<SignaturePublicKey> = signers.iter().map(|signer| signer.signature_public_key().unwrap()).collect();
+        if signature_names.is_empty() {
+            assert!(MultisigPublicKey::new(1, public_keys).is_err());
+            return;
+        }
+        assert!(MultisigPublicKey::new(0, public_keys.clone()).is_err());
+        assert!(MultisigPublicKey::new(4, public_keys.clone()).is_err());
+        assert!(MultisigPublicKey::new(1, vec![public_keys[0].clone(), public_keys[0].clone()]).is_err());
+        let legacy_body = ParsedCombinedPublicKey::new(Signature, ComponentEncoding::Legacy, public_keys[0].parse().components).encode().unwrap();
+        let legacy = SignaturePublicKey::from_bytes(&legacy_body).unwrap();
+        let with_header = SignaturePublicKey::from_bytes(&add_combined_public_key_header(Signature, &legacy_body).unwrap()).unwrap();
+        assert!(MultisigPublicKey::new(1, vec![legacy.clone(), with_header]).is_err());
+        assert!(MultisigPublicKey::new(1, vec![legacy, public_keys[0].clone()]).is_err());
+
+        // The address doesn't depend on the key order but does on the threshold
+        let multisig_public_key = MultisigPublicKey::new(2, public_keys.clone()).unwrap();
+        let address = multisig_public_key.address();
+        let reordered = MultisigPublicKey::new(2, public_keys.iter().rev().cloned().collect()).unwrap();
+        assert_eq!(reordered.address(), address);
+        assert_ne!(MultisigPublicKey::new(1, public_keys.clone()).unwrap().address(), address);
+        assert_ne!(address.as_bytes(), public_keys[0].address().as_bytes());
+
+        let test_msg = hex!("CAFEBABE");
+        let mut bundle = MultisigSignature::new(multisig_public_key.clone());
+        bundle.sign(&signers[0], &test_msg).unwrap();
+        assert!(bundle.sign(&signers[0], &test_msg).is_err());
+        assert_eq!(verify_multisig(&test_msg, &bundle, &address), Err(AddressVerificationError::ThresholdNotMet));
+        bundle.sign(&signers[2], &test_msg).unwrap();
+        assert_eq!(verify_multisig(&test_msg, &bundle, &address), Ok(()));
+        assert_eq!(MultisigSignature::from_bytes(&bundle.encode()), Ok(bundle.clone()));
+        assert_eq!(verify_multisig(b"other message", &bundle, &address), Err(AddressVerificationError::InvalidSignature));
+        assert_eq!(verify_multisig(&test_msg, &bundle, &reordered.address()), Ok(()));
+        let other_address = MultisigPublicKey::new(1, public_keys.clone()).unwrap().address();
+        assert_eq!(verify_multisig(&test_msg, &bundle, &other_address), Err(AddressVerificationError::AddressMismatch));
+
+        // A signature filed under another signer's index doesn't verify
+        let mut misattributed = MultisigSignature::new(multisig_public_key);
+        misattributed.add_signature(0, signers[0].sign(&test_msg).unwrap()).unwrap();
+        misattributed.add_signature(1, signers[2].sign(&test_msg).unwrap()).unwrap();
+        assert_eq!(verify_multisig(&test_msg, &misattributed, &address), Err(AddressVerificationError::InvalidSignature));
+        assert!(misattributed.add_signature(3, signers[2].sign(&test_msg).unwrap()).is_err());
+        assert!(bundle.sign(&HybridSeed::wrap(&signature_names, &[], &[0u8; 32]).unwrap(), &test_msg).is_err());
+        let encoded = bundle.encode();
+        assert!(MultisigSignature::from_bytes(&encoded[..encoded.len() - 1]).is_err());
+        assert!(MultisigSignature::from_bytes(&[encoded, vec![0]].concat()).is_err());
+    }
+
+    #[test]
+    fn test_merkle_address() {
+        let signature_names: Vec<String> = get_name_to_ref_mapping().keys().cloned().collect();
+        let seed = HybridSeed::wrap(&signature_names, &[], &test_seed).unwrap();
+        let public_key = seed.signature_public_key().unwrap();
+        let address = MerkleAddress::from_public_key(&public_key);
+        assert_ne!(address.as_bytes(), public_key.address().as_bytes());
+        assert_eq!(MerkleAddress::from_bytes(address.as_bytes()), Ok(address.clone()));
+        let test_msg = hex!("CAFEBABE");
+        let signature = seed.sign(&test_msg).unwrap();
+        let scheme_ids = public_key.parse().scheme_ids();
+        let bundle = MerkleSignature::new(&public_key, &signature, &scheme_ids).unwrap();
+        assert_eq!(MerkleSignature::from_bytes(&bundle.encode()), Ok(bundle.clone()));
+        if signature_names.is_empty() {
+            assert_eq!(verify_with_merkle_address(&test_msg, &bundle, &address, &[]), Err(AddressVerificationError::MissingComponents));
+            return;
+        }
+        assert_eq!(verify_with_merkle_address(&test_msg, &bundle, &address, &scheme_ids), Ok(()));
+        assert_eq!(verify_with_merkle_address(b"other message", &bundle, &address, &scheme_ids), Err(AddressVerificationError::InvalidSignature));
+
+        // Disclosing a single component is enough unless the verifier requires more
+        let partial = MerkleSignature::new(&public_key, &signature, &scheme_ids[..1]).unwrap();
+        assert_eq!(partial.components().len(), 1);
+        assert_eq!(partial.signature().scheme_ids(), scheme_ids[..1].to_vec());
+        assert_eq!(verify_with_merkle_address(&test_msg, &partial, &address, &scheme_ids[..1]), Ok(()));
+        if scheme_ids.len() > 1 {
+            assert_eq!(verify_with_merkle_address(&test_msg, &partial, &address, &scheme_ids), Err(AddressVerificationError::MissingComponents));
+        }
+        assert!(MerkleSignature::new(&public_key, &signature, &[(u16::MAX, u16::MAX)]).is_err());
+
+        let mut other_entropy = test_seed.to_vec();
+        increment_bytes(&mut other_entropy, 1);
+        let other_seed = HybridSeed::wrap(&signature_names, &[], &other_entropy).unwrap();
+        let other_bundle = MerkleSignature::new(&other_seed.signature_public_key().unwrap(), &other_seed.sign(&test_msg).unwrap(), &scheme_ids).unwrap();
+        assert_eq!(verify_with_merkle_address(&test_msg, &other_bundle, &address, &scheme_ids), Err(AddressVerificationError::AddressMismatch));
+        let encoded = bundle.encode();
+        assert!(MerkleSignature::from_bytes(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_secrets_are_redacted() {
+        let seed = HybridSeed::wrap(&[], &[], &test_seed).unwrap();
+        let parsed_seed = seed.parse();
+        let (shared_secret, _) = encapsulate(&generate_combined_public_key(KeyEncapsulation, seed.as_bytes(), KeyGenerationOptions::default()).unwrap()).unwrap();
+        let keypair = Keypair { pk: vec![1, 2, 3], sk: test_seed.to_vec() };
+        // Debug output of a byte vector would start with its first bytes
+        let entropy_prefix = format!("{:?}", &test_seed[..4]);
+        let entropy_prefix = entropy_prefix.trim_end_matches(']');
+        for debug in [format!("{:?}", seed), format!("{:?}", parsed_seed.seed), format!("{:?}", shared_secret), format!("{:?}", keypair)] {
+            assert!(debug.contains("redacted"), "{}", debug);
+            assert!(!debug.contains(entropy_prefix), "{}", debug);
+        }
+    }
+
+    #[test]
+    fn test_fake_combined_public_key() {
+        let mapping = get_name_to_ref_mapping();
+        let test_msg = hex!("CAFEBABE");
+        println!("mapping: {:?}", mapping.len());
+        for (name, scheme_ref) in mapping.iter() {
+            info!("Testing {}...", name);
+            let seed = wrap_seed(&[name.to_string()], &[], test_seed).unwrap();
+            let signature = sign_message(&seed, &test_msg).unwrap();
+            let real_combined_public_key = generate_combined_public_key(Signature, &seed, KeyGenerationOptions::default()).unwrap();
+            debug!("real_combined_public_key: {}", hex::encode(&real_combined_public_key));
+            debug!("Signature: {}", hex::encode(&signature));
+            // Try a bunch of fake combined public keys
+            for i in 0..100 {
+                let mut combined_public_key = [0u8; 48];
+                getrandom::getrandom(&mut combined_public_key).expect("Random seed could not be loaded!");
+                let is_valid = verify_message(&test_msg, &combined_public_key, &signature);
+                assert_eq!(is_valid, false);
+            }
+            // Try a bunch of fake combined public keys but real algorithm selection
+            for i in 0..100 {
+                let mut combined_public_key = [0u8; 6];
+                getrandom::getrandom(&mut combined_public_key).expect("Random seed could not be loaded!");
+                let start = hex!("0100");
+                let fake_key = vec![start.to_vec(), combined_public_key.to_vec()].concat();
+                debug!("fake_key: {}", hex::encode(&fake_key));
+                let is_valid = verify_message(&test_msg, &fake_key, &signature);
+                assert_eq!(is_valid, false);
+            }
+        }
+    }
+
+    fn check_hybrid_key_encapsulation(key_encapsulation_algorithms: &[String]) {
+        let seed = wrap_seed(&[], key_encapsulation_algorithms, test_seed).unwrap();
+        let combined_public_key = generate_combined_public_key(KeyEncapsulation, &seed, KeyGenerationOptions::default()).unwrap();
+        assert_eq!(combined_public_key, generate_combined_public_key(KeyEncapsulation, &seed, KeyGenerationOptions::default()).unwrap());
+        let (shared_secret, ciphertext) = encapsulate(&combined_public_key).unwrap();
+        debug!("Ciphertext: {}", hex::encode(&ciphertext));
+        assert_eq!(decapsulate(&seed, &ciphertext), Ok(shared_secret.clone()));
+        // Schemes that take their randomness from the rng must be reproducible with it
+        if let Ok((rng_shared_secret, rng_ciphertext)) = encapsulate_with_rng(&combined_public_key, &mut StdRng::seed_from_u64(42)) {
+            assert_eq!(decapsulate(&seed, &rng_ciphertext), Ok(rng_shared_secret.clone()));
+            assert_eq!(encapsulate_with_rng(&combined_public_key, &mut StdRng::seed_from_u64(42)), Ok((rng_shared_secret, rng_ciphertext)));
+        }
+
+        let mut tampered_ciphertext = ciphertext.clone();
+        increment_bytes(&mut tampered_ciphertext, 1);
+        assert_ne!(decapsulate(&seed, &tampered_ciphertext), Ok(shared_secret.clone()));
+
+        let mut other_seed = test_seed.clone();
+        increment_bytes(&mut other_seed, 1);
+        let other_seed = wrap_seed(&[], key_encapsulation_algorithms, other_seed).unwrap();
+        assert_ne!(decapsulate(&other_seed, &ciphertext), Ok(shared_secret));
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "saber"), ignore = "no key encapsulation scheme is compiled in")]
+    fn test_all_key_encapsulation_algorithms() {
+        let mapping = key_encapsulation::get_name_to_ref_mapping();
+        for name in mapping.keys() {
+            info!("Testing {}...", name);
+            check_hybrid_key_encapsulation(&[name.to_string()]);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "saber"), ignore = "no key encapsulation scheme is compiled in")]
+    fn test_combined_key_encapsulation_algorithms() {
+        let mapping = key_encapsulation::get_name_to_ref_mapping();
+        let names: Vec<String> = mapping.keys().cloned().collect();
+        check_hybrid_key_encapsulation(&names);
+    }
+}
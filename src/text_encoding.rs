@@ -0,0 +1,127 @@
+use crate::hybrid_signature::{HybridCiphertext, HybridSeed, HybridSignature};
+use crate::hybrid_signature::{KeyEncapsulationAddress, KeyEncapsulationPublicKey, SignatureAddress, SignaturePublicKey};
+use bech32::{FromBase32, ToBase32, Variant};
+use zeroize::Zeroizing;
+
+// Human-readable prefixes, one per artifact so a value can't be pasted where another is expected
+pub const SIGNATURE_PUBLIC_KEY_HRP: &str = "hpk";
+pub const KEY_ENCAPSULATION_PUBLIC_KEY_HRP: &str = "hkempk";
+pub const SIGNATURE_HRP: &str = "hsig";
+pub const CIPHERTEXT_HRP: &str = "hct";
+pub const SIGNATURE_ADDRESS_HRP: &str = "haddr";
+pub const KEY_ENCAPSULATION_ADDRESS_HRP: &str = "hkemaddr";
+pub const SEED_HRP: &str = "hseed";
+
+// The Bech32m checksum catches typos; the prefix catches mixed up artifacts
+pub fn encode_bech32m(hrp: &str, bytes: &[u8]) -> String {
+    return bech32::encode(hrp, bytes.to_base32(), Variant::Bech32m).expect("Human-readable prefixes are valid");
+}
+
+pub fn decode_bech32m(expected_hrp: &str, encoded: &str) -> Result<Vec<u8>, String> {
+    let (hrp, data, variant) = match bech32::decode(encoded) {
+        Ok(decoded) => decoded,
+        Err(e) => return Err(format!("Invalid Bech32m string: {}", e)),
+    };
+    if variant != Variant::Bech32m {
+        return Err("Expected a Bech32m string, got Bech32".to_string());
+    }
+    if hrp != expected_hrp {
+        return Err(format!("Expected prefix {}, got {}", expected_hrp, hrp));
+    }
+    return Vec::<u8>::from_base32(&data).map_err(|e| format!("Invalid Bech32m data: {}", e));
+}
+
+pub trait Bech32mEncoding: Sized {
+    const HRP: &'static str;
+
+    fn to_bech32m(&self) -> String;
+
+    fn from_bech32m(encoded: &str) -> Result<Self, String>;
+}
+
+macro_rules! impl_bech32m_encoding {
+    ($type:ty, $hrp:expr) => {
+        impl Bech32mEncoding for $type {
+            const HRP: &'static str = $hrp;
+
+            fn to_bech32m(&self) -> String {
+                return encode_bech32m(Self::HRP, self.as_bytes());
+            }
+
+            fn from_bech32m(encoded: &str) -> Result<Self, String> {
+                return <$type>::from_bytes(&decode_bech32m(Self::HRP, encoded)?);
+            }
+        }
+    };
+}
+
+impl_bech32m_encoding!(SignaturePublicKey, SIGNATURE_PUBLIC_KEY_HRP);
+impl_bech32m_encoding!(KeyEncapsulationPublicKey, KEY_ENCAPSULATION_PUBLIC_KEY_HRP);
+impl_bech32m_encoding!(HybridSignature, SIGNATURE_HRP);
+impl_bech32m_encoding!(HybridCiphertext, CIPHERTEXT_HRP);
+impl_bech32m_encoding!(SignatureAddress, SIGNATURE_ADDRESS_HRP);
+impl_bech32m_encoding!(KeyEncapsulationAddress, KEY_ENCAPSULATION_ADDRESS_HRP);
+
+// Seeds get their own methods so the encoded secret is zeroized like the seed itself.
+// The intermediate base32 buffer can't be, as bech32's u5 doesn't implement Zeroize.
+impl HybridSeed {
+    pub fn to_bech32m(&self) -> Zeroizing<String> {
+        return Zeroizing::new(encode_bech32m(SEED_HRP, self.as_bytes()));
+    }
+
+    pub fn from_bech32m(encoded: &str) -> Result<Self, String> {
+        let bytes = Zeroizing::new(decode_bech32m(SEED_HRP, encoded)?);
+        return HybridSeed::from_bytes(&bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_seed_with_all_schemes;
+
+    fn assert_round_trip<T: Bech32mEncoding + PartialEq + std::fmt::Debug>(value: &T) {
+        let encoded = value.to_bech32m();
+        assert!(encoded.starts_with(&format!("{}1", T::HRP)));
+        assert_eq!(&T::from_bech32m(&encoded).unwrap(), value);
+        assert_eq!(&T::from_bech32m(&encoded.to_uppercase()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_bech32m_round_trip() {
+        let seed = test_seed_with_all_schemes();
+        let signature_public_key = seed.signature_public_key().unwrap();
+        let key_encapsulation_public_key = seed.key_encapsulation_public_key().unwrap();
+        assert_round_trip(&signature_public_key);
+        assert_round_trip(&key_encapsulation_public_key);
+        assert_round_trip(&signature_public_key.address());
+        assert_round_trip(&key_encapsulation_public_key.address());
+        assert_round_trip(&seed.sign(b"CAFEBABE").unwrap());
+        assert_round_trip(&key_encapsulation_public_key.encapsulate().unwrap().1);
+
+        let encoded_seed = seed.to_bech32m();
+        assert!(encoded_seed.starts_with("hseed1"));
+        assert_eq!(HybridSeed::from_bech32m(&encoded_seed).unwrap(), seed);
+    }
+
+    #[test]
+    fn test_bech32m_detects_typos_and_wrong_prefixes() {
+        let address = test_seed_with_all_schemes().signature_public_key().unwrap().address();
+        let encoded = address.to_bech32m();
+        // Swap one data character for another valid one
+        let idx = encoded.len() - 10;
+        let replacement = if &encoded[idx..idx + 1] == "q" { "p" } else { "q" };
+        let typo = format!("{}{}{}", &encoded[..idx], replacement, &encoded[idx + 1..]);
+        assert!(SignatureAddress::from_bech32m(&typo).is_err());
+
+        // An address for the other purpose, or a different artifact altogether, is refused
+        let as_key_encapsulation_address = encode_bech32m(KEY_ENCAPSULATION_ADDRESS_HRP, address.as_bytes());
+        assert!(SignatureAddress::from_bech32m(&as_key_encapsulation_address).is_err());
+        assert!(KeyEncapsulationAddress::from_bech32m(&as_key_encapsulation_address).is_ok());
+        let bech32 = bech32::encode(SIGNATURE_ADDRESS_HRP, address.as_bytes().to_base32(), Variant::Bech32).unwrap();
+        assert!(SignatureAddress::from_bech32m(&bech32).is_err());
+        let mixed_case = format!("{}{}", &encoded[..5].to_uppercase(), &encoded[5..]);
+        assert!(SignatureAddress::from_bech32m(&mixed_case).is_err());
+        assert!(HybridSignature::from_bech32m(&encoded).is_err());
+    }
+}
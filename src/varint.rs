@@ -0,0 +1,97 @@
+// Unsigned LEB128. Values below 128 take a single byte identical to the value itself,
+// which keeps encodings that used to store single-byte identifiers unchanged.
+
+pub(crate) fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+// Only the minimal encoding of a value is accepted, so every value has exactly one encoding
+pub(crate) fn decode_varint(bytes: &[u8], idx: &mut usize) -> Result<u64, String> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = match bytes.get(*idx) {
+            Some(byte) => *byte,
+            None => return Err("Varint is truncated".to_string()),
+        };
+        *idx += 1;
+        if shift == 63 && byte > 1 {
+            return Err("Varint overflows 64 bits".to_string());
+        }
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            if byte == 0 && shift > 0 {
+                return Err("Varint is not minimally encoded".to_string());
+            }
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+pub(crate) fn decode_varint_u16(bytes: &[u8], idx: &mut usize) -> Result<u16, String> {
+    let value = decode_varint(bytes, idx)?;
+    return match u16::try_from(value) {
+        Ok(value) => Ok(value),
+        Err(_) => Err(format!("Identifier {} is out of range", value)),
+    };
+}
+
+pub(crate) fn encode_scheme_id(scheme_id: (u16, u16), out: &mut Vec<u8>) {
+    encode_varint(scheme_id.0 as u64, out);
+    encode_varint(scheme_id.1 as u64, out);
+}
+
+pub(crate) fn decode_scheme_id(bytes: &[u8], idx: &mut usize) -> Result<(u16, u16), String> {
+    let scheme_id = decode_varint_u16(bytes, idx)?;
+    let scheme_config_id = decode_varint_u16(bytes, idx)?;
+    return Ok((scheme_id, scheme_config_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_round_trip() {
+        for value in [0u64, 1, 127, 128, 255, 256, 300, 16383, 16384, u16::MAX as u64, u32::MAX as u64, u64::MAX] {
+            let mut encoded = Vec::new();
+            encode_varint(value, &mut encoded);
+            let mut idx = 0;
+            assert_eq!(decode_varint(&encoded, &mut idx), Ok(value));
+            assert_eq!(idx, encoded.len());
+        }
+        let mut encoded = Vec::new();
+        encode_varint(300, &mut encoded);
+        assert_eq!(encoded, vec![0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_small_values_are_single_bytes() {
+        for value in 0..128u64 {
+            let mut encoded = Vec::new();
+            encode_varint(value, &mut encoded);
+            assert_eq!(encoded, vec![value as u8]);
+        }
+    }
+
+    #[test]
+    fn test_malformed_varints() {
+        let mut idx = 0;
+        assert!(decode_varint(&[0x80], &mut idx).is_err());
+        idx = 0;
+        assert!(decode_varint(&[0x81, 0x00], &mut idx).is_err());
+        idx = 0;
+        assert!(decode_varint(&[0xff; 10], &mut idx).is_err());
+        idx = 0;
+        assert!(decode_varint_u16(&[0x80, 0x80, 0x04], &mut idx).is_err());
+    }
+}
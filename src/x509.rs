@@ -0,0 +1,335 @@
+use crate::hybrid_signature::{CombinedPublicKey, HybridSignature, HybridSigner, KeyEncapsulationPublicKey, KeyPurpose, SignaturePublicKey};
+use crate::key_containers::{HYBRID_SIGNATURE_ALGORITHM_OID, KEY_ENCAPSULATION_PUBLIC_KEY_OID, SIGNATURE_PUBLIC_KEY_OID};
+use crate::schemes::AlgorithmPurpose;
+use std::str::FromStr;
+use std::time::Duration;
+use x509_cert::certificate::Version;
+use x509_cert::der::asn1::{BitString, GeneralizedTime, OctetString, SetOfVec, UtcTime};
+use x509_cert::der::oid::AssociatedOid;
+use x509_cert::der::{Decode, Encode};
+use x509_cert::ext::pkix::{BasicConstraints, KeyUsage, KeyUsages};
+use x509_cert::ext::Extension;
+use x509_cert::name::Name;
+use x509_cert::request::{CertReq, CertReqInfo};
+use x509_cert::serial_number::SerialNumber;
+use x509_cert::spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned};
+use x509_cert::time::{Time, Validity};
+use x509_cert::{Certificate, TbsCertificate};
+
+// What an issuer puts into a certificate besides the subject's public key. Names are RFC 4514
+// strings like "CN=gateway-7,O=Example" and times are Unix seconds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertificateParams {
+    pub serial_number: u64,
+    pub subject: String,
+    pub not_before: u64,
+    pub not_after: u64,
+    pub is_ca: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedCertificate {
+    pub serial_number: Vec<u8>,
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: u64,
+    pub not_after: u64,
+    pub is_ca: bool,
+    pub purpose: AlgorithmPurpose,
+    subject_public_key_info: Vec<u8>,
+    tbs_certificate: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+fn hybrid_signature_algorithm() -> AlgorithmIdentifierOwned {
+    return AlgorithmIdentifierOwned {
+        oid: HYBRID_SIGNATURE_ALGORITHM_OID,
+        parameters: None,
+    };
+}
+
+fn der_error(what: &str) -> impl Fn(x509_cert::der::Error) -> String + '_ {
+    return move |e| format!("Invalid {}: {}", what, e);
+}
+
+fn parse_name(name: &str) -> Result<Name, String> {
+    return Name::from_str(name).map_err(der_error("name"));
+}
+
+fn subject_public_key_info<P: KeyPurpose>(public_key: &CombinedPublicKey<P>) -> SubjectPublicKeyInfoOwned {
+    return SubjectPublicKeyInfoOwned::from_der(&public_key.to_public_key_der()).expect("SubjectPublicKeyInfo was just encoded");
+}
+
+// RFC 5280 wants UTCTime up to 2049 and GeneralizedTime from 2050 on
+fn to_time(unix_seconds: u64) -> Result<Time, String> {
+    let duration = Duration::from_secs(unix_seconds);
+    if let Ok(utc_time) = UtcTime::from_unix_duration(duration) {
+        return Ok(Time::UtcTime(utc_time));
+    }
+    return GeneralizedTime::from_unix_duration(duration).map(Time::GeneralTime).map_err(der_error("time"));
+}
+
+fn extension<T: AssociatedOid + Encode>(value: &T, critical: bool) -> Result<Extension, String> {
+    let extn_value = value.to_der().map_err(der_error("extension"))?;
+    return Ok(Extension {
+        extn_id: T::OID,
+        critical,
+        extn_value: OctetString::new(extn_value).map_err(der_error("extension"))?,
+    });
+}
+
+fn sign_der<S: HybridSigner>(signer: &S, tbs: &[u8]) -> Result<BitString, String> {
+    let signature = signer.sign(tbs)?;
+    return BitString::from_bytes(signature.as_bytes()).map_err(der_error("signature"));
+}
+
+// PKCS#10 request whose proof of possession is a hybrid signature by every component
+pub fn create_csr<S: HybridSigner>(signer: &S, subject: &str) -> Result<Vec<u8>, String> {
+    let info = CertReqInfo {
+        version: x509_cert::request::Version::V1,
+        subject: parse_name(subject)?,
+        public_key: subject_public_key_info(&signer.signature_public_key()?),
+        attributes: SetOfVec::new(),
+    };
+    let signature = sign_der(signer, &info.to_der().map_err(der_error("CSR"))?)?;
+    let csr = CertReq {
+        info,
+        algorithm: hybrid_signature_algorithm(),
+        signature,
+    };
+    return csr.to_der().map_err(der_error("CSR"));
+}
+
+// Checks the proof of possession and returns the requested subject and its public key
+pub fn verify_csr(csr: &[u8]) -> Result<(String, SignaturePublicKey), String> {
+    let csr = CertReq::from_der(csr).map_err(der_error("CSR"))?;
+    if csr.algorithm != hybrid_signature_algorithm() {
+        return Err(format!("CSR is signed with {}, not a hybrid signature", csr.algorithm.oid));
+    }
+    let public_key = SignaturePublicKey::from_public_key_der(&csr.info.public_key.to_der().map_err(der_error("CSR"))?)?;
+    let signature = match csr.signature.as_bytes() {
+        Some(signature) => HybridSignature::from_bytes(signature)?,
+        None => return Err("CSR signature has unused bits".to_string()),
+    };
+    if !public_key.verify(&csr.info.to_der().map_err(der_error("CSR"))?, &signature) {
+        return Err("CSR signature verification failed".to_string());
+    }
+    return Ok((csr.info.subject.to_string(), public_key));
+}
+
+// X.509 v3 certificate for a signature or key encapsulation key, signed by issuer. CA
+// certificates may sign certificates, end-entity ones sign data or receive encapsulations.
+pub fn issue_certificate<S: HybridSigner, P: KeyPurpose>(issuer: &S, issuer_name: &str, subject_public_key: &CombinedPublicKey<P>, params: &CertificateParams) -> Result<Vec<u8>, String> {
+    if params.is_ca && P::PURPOSE != AlgorithmPurpose::Signature {
+        return Err("CA certificates need a signature key".to_string());
+    }
+    let key_usage = match (params.is_ca, P::PURPOSE) {
+        (true, _) => KeyUsages::DigitalSignature | KeyUsages::KeyCertSign,
+        (false, AlgorithmPurpose::Signature) => KeyUsages::DigitalSignature.into(),
+        (false, AlgorithmPurpose::KeyEncapsulation) => KeyUsages::KeyEncipherment.into(),
+    };
+    let basic_constraints = BasicConstraints {
+        ca: params.is_ca,
+        path_len_constraint: None,
+    };
+    let tbs_certificate = TbsCertificate {
+        version: Version::V3,
+        serial_number: SerialNumber::new(&params.serial_number.to_be_bytes()).map_err(der_error("serial number"))?,
+        signature: hybrid_signature_algorithm(),
+        issuer: parse_name(issuer_name)?,
+        validity: Validity {
+            not_before: to_time(params.not_before)?,
+            not_after: to_time(params.not_after)?,
+        },
+        subject: parse_name(&params.subject)?,
+        subject_public_key_info: subject_public_key_info(subject_public_key),
+        issuer_unique_id: None,
+        subject_unique_id: None,
+        extensions: Some(vec![extension(&basic_constraints, true)?, extension(&KeyUsage(key_usage), true)?]),
+    };
+    let signature = sign_der(issuer, &tbs_certificate.to_der().map_err(der_error("certificate"))?)?;
+    let certificate = Certificate {
+        tbs_certificate,
+        signature_algorithm: hybrid_signature_algorithm(),
+        signature,
+    };
+    return certificate.to_der().map_err(der_error("certificate"));
+}
+
+pub fn issue_self_signed_certificate<S: HybridSigner>(signer: &S, params: &CertificateParams) -> Result<Vec<u8>, String> {
+    return issue_certificate(signer, &params.subject, &signer.signature_public_key()?, params);
+}
+
+pub fn parse_certificate(certificate: &[u8]) -> Result<ParsedCertificate, String> {
+    let certificate = Certificate::from_der(certificate).map_err(der_error("certificate"))?;
+    let tbs = &certificate.tbs_certificate;
+    if certificate.signature_algorithm != hybrid_signature_algorithm() || tbs.signature != hybrid_signature_algorithm() {
+        return Err(format!("Certificate is signed with {}, not a hybrid signature", certificate.signature_algorithm.oid));
+    }
+    let purpose = match tbs.subject_public_key_info.algorithm.oid {
+        oid if oid == SIGNATURE_PUBLIC_KEY_OID => AlgorithmPurpose::Signature,
+        oid if oid == KEY_ENCAPSULATION_PUBLIC_KEY_OID => AlgorithmPurpose::KeyEncapsulation,
+        oid => return Err(format!("Certificate key has algorithm {}, which is not a combined public key", oid)),
+    };
+    // Extensions this crate doesn't understand must not be critical
+    for extension in tbs.extensions.as_deref().unwrap_or(&[]).iter() {
+        if extension.critical && extension.extn_id != BasicConstraints::OID && extension.extn_id != KeyUsage::OID {
+            return Err(format!("Certificate has unsupported critical extension {}", extension.extn_id));
+        }
+    }
+    let basic_constraints = tbs.get::<BasicConstraints>().map_err(der_error("basic constraints"))?;
+    let key_usage = tbs.get::<KeyUsage>().map_err(der_error("key usage"))?;
+    let is_ca = match (basic_constraints, key_usage) {
+        (Some((_, basic_constraints)), Some((_, key_usage))) => basic_constraints.ca && key_usage.0.contains(KeyUsages::KeyCertSign),
+        _ => false,
+    };
+    let signature = match certificate.signature.as_bytes() {
+        Some(signature) => signature.to_vec(),
+        None => return Err("Certificate signature has unused bits".to_string()),
+    };
+    let parsed = ParsedCertificate {
+        serial_number: tbs.serial_number.as_bytes().to_vec(),
+        subject: tbs.subject.to_string(),
+        issuer: tbs.issuer.to_string(),
+        not_before: tbs.validity.not_before.to_unix_duration().as_secs(),
+        not_after: tbs.validity.not_after.to_unix_duration().as_secs(),
+        is_ca,
+        purpose,
+        subject_public_key_info: tbs.subject_public_key_info.to_der().map_err(der_error("certificate"))?,
+        tbs_certificate: tbs.to_der().map_err(der_error("certificate"))?,
+        signature,
+    };
+    // Catches keys that carry the right OID but don't parse
+    match purpose {
+        AlgorithmPurpose::Signature => parsed.signature_public_key().map(|_| ())?,
+        AlgorithmPurpose::KeyEncapsulation => parsed.key_encapsulation_public_key().map(|_| ())?,
+    }
+    return Ok(parsed);
+}
+
+impl ParsedCertificate {
+    pub fn signature_public_key(&self) -> Result<SignaturePublicKey, String> {
+        return SignaturePublicKey::from_public_key_der(&self.subject_public_key_info);
+    }
+
+    pub fn key_encapsulation_public_key(&self) -> Result<KeyEncapsulationPublicKey, String> {
+        return KeyEncapsulationPublicKey::from_public_key_der(&self.subject_public_key_info);
+    }
+
+    pub fn is_valid_at(&self, unix_seconds: u64) -> bool {
+        return self.not_before <= unix_seconds && unix_seconds <= self.not_after;
+    }
+
+    // Every component of the issuer key has to have signed
+    pub fn is_signed_by(&self, issuer: &SignaturePublicKey) -> bool {
+        return match HybridSignature::from_bytes(&self.signature) {
+            Ok(signature) => issuer.verify(&self.tbs_certificate, &signature),
+            Err(_) => false,
+        };
+    }
+}
+
+// Validates a chain given leaf first. Every certificate has to be valid at the given time and
+// signed by the next one, which has to be a CA with a matching subject; the last certificate
+// (a self-signed root or an intermediate) has to be signed by one of the trusted root keys.
+pub fn verify_certificate_chain(chain: &[Vec<u8>], trusted_roots: &[SignaturePublicKey], unix_seconds: u64) -> Result<ParsedCertificate, String> {
+    let certificates = chain.iter().map(|certificate| parse_certificate(certificate)).collect::<Result<Vec<ParsedCertificate>, String>>()?;
+    if certificates.is_empty() {
+        return Err("Certificate chain is empty".to_string());
+    }
+    for (idx, certificate) in certificates.iter().enumerate() {
+        if !certificate.is_valid_at(unix_seconds) {
+            return Err(format!("Certificate {} ({}) is not valid at {}", idx, certificate.subject, unix_seconds));
+        }
+        let signed = match certificates.get(idx + 1) {
+            Some(issuer) => {
+                if !issuer.is_ca || issuer.purpose != AlgorithmPurpose::Signature {
+                    return Err(format!("Certificate {} ({}) is not a CA", idx + 1, issuer.subject));
+                }
+                if issuer.subject != certificate.issuer {
+                    return Err(format!("Certificate {} is issued by {}, not {}", idx, certificate.issuer, issuer.subject));
+                }
+                certificate.is_signed_by(&issuer.signature_public_key()?)
+            }
+            None => trusted_roots.iter().any(|root| certificate.is_signed_by(root)),
+        };
+        if !signed {
+            return Err(format!("Certificate {} ({}) signature verification failed", idx, certificate.subject));
+        }
+    }
+    return Ok(certificates[0].clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_seed_with_offset;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn params(serial_number: u64, subject: &str, is_ca: bool) -> CertificateParams {
+        return CertificateParams {
+            serial_number,
+            subject: subject.to_string(),
+            not_before: NOW - 3600,
+            not_after: NOW + 3600,
+            is_ca,
+        };
+    }
+
+    #[test]
+    fn test_csr_round_trip() {
+        let seed = test_seed_with_offset(0);
+        let csr = create_csr(&seed, "CN=gateway-7,O=Example").unwrap();
+        assert_eq!(verify_csr(&csr), Ok(("CN=gateway-7,O=Example".to_string(), seed.signature_public_key().unwrap())));
+        assert!(verify_csr(&csr[..csr.len() - 1]).is_err());
+        assert!(create_csr(&seed, "not a name").is_err());
+    }
+
+    #[test]
+    fn test_certificate_chain() {
+        let root = test_seed_with_offset(0);
+        let intermediate = test_seed_with_offset(1);
+        let leaf = test_seed_with_offset(2);
+        let root_certificate = issue_self_signed_certificate(&root, &params(1, "CN=Root", true)).unwrap();
+        let intermediate_certificate = issue_certificate(&root, "CN=Root", &intermediate.signature_public_key().unwrap(), &params(2, "CN=Intermediate", true)).unwrap();
+        let leaf_certificate = issue_certificate(&intermediate, "CN=Intermediate", &leaf.key_encapsulation_public_key().unwrap(), &params(3, "CN=gateway-7", false)).unwrap();
+
+        let chain = vec![leaf_certificate.clone(), intermediate_certificate.clone(), root_certificate.clone()];
+        let trusted_roots = [root.signature_public_key().unwrap()];
+        let parsed_leaf = verify_certificate_chain(&chain, &trusted_roots, NOW).unwrap();
+        assert_eq!(parsed_leaf.subject, "CN=gateway-7");
+        assert_eq!(parsed_leaf.issuer, "CN=Intermediate");
+        assert_eq!(parsed_leaf.serial_number, vec![3]);
+        assert_eq!(parsed_leaf.purpose, AlgorithmPurpose::KeyEncapsulation);
+        assert!(!parsed_leaf.is_ca);
+        assert_eq!(parsed_leaf.key_encapsulation_public_key(), Ok(leaf.key_encapsulation_public_key().unwrap()));
+        // The root itself may be left out of the chain
+        assert!(verify_certificate_chain(&chain[..2], &trusted_roots, NOW).is_ok());
+
+        assert!(verify_certificate_chain(&chain, &trusted_roots, NOW + 7200).is_err());
+        assert!(verify_certificate_chain(&chain, &[], NOW).is_err());
+        assert!(verify_certificate_chain(&[leaf_certificate.clone(), root_certificate.clone()], &trusted_roots, NOW).is_err());
+        // A non-CA can't issue certificates, even when the signature is fine
+        let end_entity = issue_certificate(&root, "CN=Root", &intermediate.signature_public_key().unwrap(), &params(4, "CN=Intermediate", false)).unwrap();
+        assert!(verify_certificate_chain(&[leaf_certificate, end_entity, root_certificate], &trusted_roots, NOW).is_err());
+        assert!(issue_certificate(&root, "CN=Root", &leaf.key_encapsulation_public_key().unwrap(), &params(5, "CN=KEM CA", true)).is_err());
+    }
+
+    #[test]
+    fn test_tampered_certificate_is_rejected() {
+        let root = test_seed_with_offset(0);
+        let certificate = issue_self_signed_certificate(&root, &params(1, "CN=Root", true)).unwrap();
+        let parsed = parse_certificate(&certificate).unwrap();
+        assert!(parsed.is_signed_by(&root.signature_public_key().unwrap()));
+        let other = test_seed_with_offset(1);
+        if root.signature_public_key().unwrap().parse().component_count() > 0 {
+            assert!(!parsed.is_signed_by(&other.signature_public_key().unwrap()));
+            // Flip a byte in the subject name
+            let idx = certificate.windows(4).position(|window| window == b"Root").unwrap();
+            let mut tampered = certificate.clone();
+            tampered[idx] = b'B';
+            assert!(verify_certificate_chain(&[tampered], &[root.signature_public_key().unwrap()], NOW).is_err());
+        }
+    }
+}